@@ -0,0 +1,49 @@
+#import bevy_pbr::{
+    mesh_functions::{get_model_matrix, mesh_normal_local_to_world, mesh_position_local_to_world},
+    mesh_view_bindings::view,
+    view_transformations::position_world_to_clip,
+}
+
+struct OutlineMaterial {
+    color: vec4<f32>,
+    // In world units, or in physical pixels with `constant_width`.
+    thickness: f32,
+    constant_width: u32,
+};
+
+@group(2) @binding(0) var<uniform> material: OutlineMaterial;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) outline_normal: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let model = get_model_matrix(vertex.instance_index);
+    let world_position = mesh_position_local_to_world(model, vec4<f32>(vertex.position, 1.0)).xyz;
+    // Transformed by the inverse transpose, so the outline is equally thick along every axis of a
+    // scaled mesh.
+    let normal = mesh_normal_local_to_world(vertex.outline_normal, vertex.instance_index);
+
+    var thickness = material.thickness;
+    if material.constant_width != 0u {
+        // The size of a pixel at the depth of the vertex.
+        let w = position_world_to_clip(world_position).w;
+        thickness *= 2.0 * w / (view.projection[1][1] * view.viewport.w);
+    }
+
+    var out: VertexOutput;
+    out.position = position_world_to_clip(world_position + normal * thickness);
+    return out;
+}
+
+@fragment
+fn fragment() -> @location(0) vec4<f32> {
+    return material.color;
+}
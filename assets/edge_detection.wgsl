@@ -0,0 +1,77 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import bevy_render::view::View
+
+struct EdgeDetection {
+    color: vec4<f32>,
+    // In physical pixels.
+    width: f32,
+    depth_threshold: f32,
+    normal_threshold: f32,
+};
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+#ifdef MULTISAMPLED
+@group(0) @binding(1) var depth_texture: texture_depth_multisampled_2d;
+@group(0) @binding(2) var normal_texture: texture_multisampled_2d<f32>;
+#else
+@group(0) @binding(1) var depth_texture: texture_depth_2d;
+@group(0) @binding(2) var normal_texture: texture_2d<f32>;
+#endif
+@group(0) @binding(3) var<uniform> settings: EdgeDetection;
+@group(0) @binding(4) var<uniform> view: View;
+
+struct Surface {
+    // The distance from the camera, or 0.0 where nothing is drawn.
+    depth: f32,
+    normal: vec3<f32>,
+};
+
+fn surface_at(pixel: vec2<i32>) -> Surface {
+    let size = vec2<i32>(textureDimensions(depth_texture));
+    let p = clamp(pixel, vec2<i32>(0), size - 1);
+    // The first sample when multisampled, otherwise the first mip level.
+    let depth = textureLoad(depth_texture, p, 0);
+
+    var surface: Surface;
+    surface.normal = textureLoad(normal_texture, p, 0).xyz * 2.0 - 1.0;
+    // The depth is reversed, so 0.0 is the far plane the prepass is cleared to.
+    if depth > 0.0 {
+        let view_position = view.inverse_projection * vec4<f32>(0.0, 0.0, depth, 1.0);
+        surface.depth = -view_position.z / view_position.w;
+    }
+    return surface;
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.position.xy);
+    let color = textureLoad(screen_texture, pixel, 0);
+
+    // Neighbors on both sides, so the line is centered on the edge.
+    let r = i32(max(round(settings.width / 2.0), 1.0));
+    var offsets = array<vec2<i32>, 4>(
+        vec2<i32>(r, 0),
+        vec2<i32>(-r, 0),
+        vec2<i32>(0, r),
+        vec2<i32>(0, -r),
+    );
+
+    let center = surface_at(pixel);
+    var edge = false;
+    for (var i = 0; i < 4; i++) {
+        let neighbor = surface_at(pixel + offsets[i]);
+        if (center.depth > 0.0) != (neighbor.depth > 0.0) {
+            // The silhouette against the background.
+            edge = true;
+        } else if center.depth > 0.0 {
+            let depth = abs(center.depth - neighbor.depth) / min(center.depth, neighbor.depth);
+            let normal = 1.0 - dot(center.normal, neighbor.normal);
+            edge = edge || depth > settings.depth_threshold || normal > settings.normal_threshold;
+        }
+    }
+
+    if !edge {
+        return color;
+    }
+    return vec4<f32>(mix(color.rgb, settings.color.rgb, settings.color.a), color.a);
+}
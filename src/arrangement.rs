@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Height of the ground plane references rest on in [`Layout::Ground`].
+pub const GROUND_HEIGHT: f32 = -1.5;
+/// Half extents of the volume references are scattered in. Small enough that every reference
+/// stays in view with the default camera.
+const SCATTER_EXTENTS: Vec3 = Vec3::new(3.0, 2.0, 2.5);
+/// How often a reference is moved to a new random position before giving up on avoiding overlap.
+const MAX_ATTEMPTS: usize = 200;
+
+/// How many references are shown at once and how they are placed.
+#[derive(Resource, Debug, Clone)]
+pub struct ArrangementSettings {
+    /// Number of references shown at once, 1 shows a single reference in the center.
+    pub count: usize,
+    pub layout: Layout,
+    /// Let references intersect each other.
+    pub allow_intersections: bool,
+}
+
+impl Default for ArrangementSettings {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            layout: Layout::Scattered,
+            allow_intersections: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Floating at random positions and depths.
+    Scattered,
+    /// Resting on a ground plane at random depths.
+    Ground,
+}
+
+/// The size of a posed reference, as needed for arranging it.
+#[derive(Debug, Clone, Copy)]
+pub struct ArrangedObject {
+    /// Radius of the bounding sphere around the reference's origin.
+    pub radius: f32,
+    /// How far the lowest point of the reference is below its origin.
+    pub bottom: f32,
+}
+
+impl ArrangementSettings {
+    /// Returns the position of every object. Unless intersections are allowed, the bounding
+    /// spheres of the objects don't overlap (or circles on the ground plane in [`Layout::Ground`]).
+    /// If there isn't enough room, the positions with the least overlap found are used.
+    pub fn arrange(&self, objects: &[ArrangedObject], rng: &mut impl Rng) -> Vec<Vec3> {
+        if let [object] = objects {
+            return vec![match self.layout {
+                Layout::Scattered => Vec3::ZERO,
+                Layout::Ground => Vec3::new(0.0, GROUND_HEIGHT + object.bottom, 0.0),
+            }];
+        }
+
+        let mut positions: Vec<Vec3> = Vec::with_capacity(objects.len());
+        for object in objects {
+            let mut best = (f32::INFINITY, Vec3::ZERO);
+            for _ in 0..MAX_ATTEMPTS {
+                let candidate = self.random_position(object, rng);
+                let overlap = positions
+                    .iter()
+                    .zip(objects)
+                    .map(|(position, other)| {
+                        let distance = match self.layout {
+                            Layout::Scattered => position.distance(candidate),
+                            Layout::Ground => position.xz().distance(candidate.xz()),
+                        };
+                        (object.radius + other.radius - distance).max(0.0)
+                    })
+                    .sum::<f32>();
+
+                if overlap < best.0 {
+                    best = (overlap, candidate);
+                }
+                if overlap == 0.0 || self.allow_intersections {
+                    break;
+                }
+            }
+            positions.push(best.1);
+        }
+        positions
+    }
+
+    fn random_position(&self, object: &ArrangedObject, rng: &mut impl Rng) -> Vec3 {
+        let mut random = |extent: f32| rng.gen_range(-extent..=extent);
+        match self.layout {
+            Layout::Scattered => Vec3::new(
+                random(SCATTER_EXTENTS.x),
+                random(SCATTER_EXTENTS.y),
+                random(SCATTER_EXTENTS.z),
+            ),
+            Layout::Ground => Vec3::new(
+                random(SCATTER_EXTENTS.x),
+                GROUND_HEIGHT + object.bottom,
+                random(SCATTER_EXTENTS.z),
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_arrange_without_intersections() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let objects = [ArrangedObject {
+        radius: 0.8,
+        bottom: 0.5,
+    }; 4];
+
+    for layout in [Layout::Scattered, Layout::Ground] {
+        let settings = ArrangementSettings {
+            count: objects.len(),
+            layout,
+            allow_intersections: false,
+        };
+        let positions = settings.arrange(&objects, &mut rng);
+        assert_eq!(positions.len(), objects.len());
+
+        for (i, a) in positions.iter().enumerate() {
+            for b in positions[i + 1..].iter() {
+                assert!(a.distance(*b) >= 1.6, "{layout:?}: {a} {b}");
+            }
+            if layout == Layout::Ground {
+                assert_eq!(a.y, GROUND_HEIGHT + 0.5);
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use bevy::{
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseButtonInput, MouseMotion, MouseWheel},
+    },
+    prelude::*,
+    window::{PrimaryWindow, WindowFocused, WindowOccluded},
+};
+
+use crate::timer::{PoseTimer, Timer, TimerSet};
+
+/// Pauses the [`PoseTimer`] while the user is away and resumes it with a countdown when they return.
+pub struct AutoPausePlugin;
+
+impl Plugin for AutoPausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutoPause>().add_systems(
+            Update,
+            // Chained, as both pause and resume the same timer.
+            (auto_pause_on_focus, auto_pause_on_idle)
+                .chain()
+                .before(TimerSet),
+        );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct AutoPause {
+    /// Pause when the window loses focus or is minimized.
+    pub on_focus_loss: bool,
+    /// Pause after this long without any input.
+    pub idle: Option<Duration>,
+    /// Time before the timer continues after the user returned.
+    pub resume_countdown: Duration,
+    last_input: Duration,
+}
+
+impl Default for AutoPause {
+    fn default() -> Self {
+        Self {
+            on_focus_loss: true,
+            idle: None,
+            resume_countdown: Duration::from_secs(3),
+            last_input: Duration::ZERO,
+        }
+    }
+}
+
+/// Marks a timer that was paused automatically, so only those get resumed automatically and a
+/// pause by the user is left alone.
+#[derive(Component)]
+struct AutoPaused;
+
+fn auto_pause_on_focus(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut settings: ResMut<AutoPause>,
+    mut focus_events: EventReader<WindowFocused>,
+    mut occluded_events: EventReader<WindowOccluded>,
+    mut timers: Query<(Entity, &mut Timer, Has<AutoPaused>), With<PoseTimer>>,
+) {
+    // The last event of the frame decides, e.g. losing and regaining focus in one frame is no-op.
+    let focused = focus_events
+        .read()
+        .map(|e| e.focused)
+        .chain(occluded_events.read().map(|e| !e.occluded))
+        .last();
+    let Some(focused) = focused else {
+        return;
+    };
+    // Input while the window is unfocused isn't counted, so returning to it counts instead and
+    // the timer isn't paused again for being idle.
+    if focused {
+        settings.last_input = time.elapsed();
+    }
+
+    for (entity, mut timer, auto_paused) in timers.iter_mut() {
+        if !focused && settings.on_focus_loss && !timer.is_paused() {
+            timer.set_pause(true);
+            commands.entity(entity).insert(AutoPaused);
+        } else if focused && auto_paused {
+            timer.resume_with_countdown(settings.resume_countdown);
+            commands.entity(entity).remove::<AutoPaused>();
+        }
+    }
+}
+
+fn auto_pause_on_idle(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut settings: ResMut<AutoPause>,
+    mut keyboard: EventReader<KeyboardInput>,
+    mut mouse_buttons: EventReader<MouseButtonInput>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut timers: Query<(Entity, &mut Timer, Has<AutoPaused>), With<PoseTimer>>,
+) {
+    let input_count = keyboard.read().count()
+        + mouse_buttons.read().count()
+        + mouse_motion.read().count()
+        + mouse_wheel.read().count();
+    // Mouse motion is reported even when the window isn't focused, which shouldn't count.
+    let focused = windows.iter().any(|window| window.focused);
+    let had_input = input_count > 0 && focused;
+    if had_input {
+        settings.last_input = time.elapsed();
+    }
+
+    let Some(idle) = settings.idle else {
+        return;
+    };
+    let is_idle = time.elapsed() - settings.last_input >= idle;
+
+    for (entity, mut timer, auto_paused) in timers.iter_mut() {
+        if is_idle && !timer.is_paused() {
+            timer.set_pause(true);
+            commands.entity(entity).insert(AutoPaused);
+        } else if had_input && auto_paused {
+            timer.resume_with_countdown(settings.resume_countdown);
+            commands.entity(entity).remove::<AutoPaused>();
+        }
+    }
+}
+
+#[cfg(test)]
+fn auto_pause_test_app(settings: AutoPause) -> (App, Entity) {
+    let mut app = App::new();
+    app.init_resource::<Time<Real>>()
+        .insert_resource(settings)
+        .add_event::<WindowFocused>()
+        .add_event::<WindowOccluded>()
+        .add_event::<KeyboardInput>()
+        .add_event::<MouseButtonInput>()
+        .add_event::<MouseMotion>()
+        .add_event::<MouseWheel>()
+        .add_systems(Update, (auto_pause_on_focus, auto_pause_on_idle).chain());
+    app.world.spawn((Window::default(), PrimaryWindow));
+    let timer = app
+        .world
+        .spawn((Timer::new(Duration::from_secs(60)), PoseTimer))
+        .id();
+    (app, timer)
+}
+
+#[cfg(test)]
+fn set_focus(app: &mut App, focused: bool, delta: Duration) {
+    let window = app
+        .world
+        .query_filtered::<Entity, With<PrimaryWindow>>()
+        .single(&app.world);
+    app.world
+        .query::<&mut Window>()
+        .single_mut(&mut app.world)
+        .focused = focused;
+    app.world.send_event(WindowFocused { window, focused });
+    app.world.resource_mut::<Time<Real>>().advance_by(delta);
+    app.update();
+}
+
+#[test]
+fn test_auto_pause_on_focus() {
+    let (mut app, timer) = auto_pause_test_app(AutoPause::default());
+
+    set_focus(&mut app, false, Duration::from_secs(1));
+    assert!(app.world.get::<Timer>(timer).unwrap().is_paused());
+    assert!(app.world.get::<AutoPaused>(timer).is_some());
+
+    set_focus(&mut app, true, Duration::from_secs(1));
+    let paused = app.world.get::<Timer>(timer).unwrap();
+    assert!(!paused.is_paused());
+    assert_eq!(
+        paused.resume_countdown(),
+        Some(AutoPause::default().resume_countdown)
+    );
+    assert!(app.world.get::<AutoPaused>(timer).is_none());
+}
+
+#[test]
+fn test_auto_pause_on_focus_after_idle() {
+    let (mut app, timer) = auto_pause_test_app(AutoPause {
+        idle: Some(Duration::from_secs(30)),
+        ..default()
+    });
+
+    // Away for longer than the idle time, without any input.
+    set_focus(&mut app, false, Duration::from_secs(1));
+    app.world
+        .resource_mut::<Time<Real>>()
+        .advance_by(Duration::from_secs(60));
+    app.update();
+    assert!(app.world.get::<Timer>(timer).unwrap().is_paused());
+
+    // Returning resumes the timer instead of pausing it again for being idle.
+    set_focus(&mut app, true, Duration::from_millis(10));
+    assert!(!app.world.get::<Timer>(timer).unwrap().is_paused());
+    assert!(app.world.get::<AutoPaused>(timer).is_none());
+}
+
+#[test]
+fn test_auto_pause_on_idle() {
+    let (mut app, timer) = auto_pause_test_app(AutoPause {
+        idle: Some(Duration::from_secs(30)),
+        ..default()
+    });
+
+    app.world
+        .resource_mut::<Time<Real>>()
+        .advance_by(Duration::from_secs(31));
+    app.update();
+    assert!(app.world.get::<Timer>(timer).unwrap().is_paused());
+
+    // Any input resumes it.
+    app.world.send_event(MouseWheel {
+        unit: bevy::input::mouse::MouseScrollUnit::Line,
+        x: 0.0,
+        y: 1.0,
+        window: Entity::PLACEHOLDER,
+    });
+    app.update();
+    assert!(!app.world.get::<Timer>(timer).unwrap().is_paused());
+}
@@ -0,0 +1,441 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    math::primitives::Plane3d,
+    prelude::*,
+    render::camera::ScalingMode,
+    window::PrimaryWindow,
+};
+use bevy_egui::EguiContexts;
+use rand::Rng;
+
+use crate::references::{NewPose, ReferenceSet};
+use crate::wrapping_cursor::WrappingCursorState;
+use crate::MainCamera;
+
+/// Distance of the camera to the reference with the default field of view.
+const BASE_DISTANCE: f32 = 8.0;
+const BASE_FOV: f32 = PI / 4.0;
+
+/// Radians the camera orbits per pixel the mouse moves.
+const ORBIT_SPEED: f32 = 0.008;
+/// How far the camera may orbit above or below the focus, short of looking straight down or up.
+const MAX_ELEVATION: f32 = 85.0 * PI / 180.0;
+/// Logarithmic zoom per pixel of dragging and per line of scrolling.
+const DOLLY_SPEED: f32 = 0.01;
+const ZOOM_SPEED: f32 = 0.15;
+/// How much farther than the framed distance the camera can be moved away.
+const MAX_ZOOM_OUT: f32 = 10.0;
+/// Restores the view chosen for the current pose.
+pub const RESET_VIEW_KEY: KeyCode = KeyCode::Home;
+
+pub struct MainCameraPlugin;
+
+impl Plugin for MainCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LensSettings>().add_systems(
+            Update,
+            (frame_pose, orbit_camera).chain().after(ReferenceSet),
+        );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct LensSettings {
+    pub projection: LensProjection,
+    /// Pick a random field of view between `min_fov` and `max_fov` for every pose.
+    pub random_fov: bool,
+    /// Vertical field of view in radians, used if `random_fov` is false.
+    pub fov: f32,
+    pub min_fov: f32,
+    pub max_fov: f32,
+    /// Move the camera so the references fill the view.
+    pub auto_frame: bool,
+    /// Space around the framed references, relative to their size.
+    pub margin: f32,
+}
+
+impl Default for LensSettings {
+    fn default() -> Self {
+        Self {
+            projection: LensProjection::Perspective,
+            random_fov: false,
+            fov: BASE_FOV,
+            min_fov: 15f32.to_radians(),
+            max_fov: 100f32.to_radians(),
+            auto_frame: true,
+            margin: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LensProjection {
+    Perspective,
+    /// No perspective at all, parallel lines stay parallel.
+    Orthographic,
+}
+
+impl LensSettings {
+    /// Samples the field of view for a pose.
+    pub fn sample_fov(&self, rng: &mut impl Rng) -> f32 {
+        if !self.random_fov {
+            self.fov
+        } else if self.min_fov < self.max_fov {
+            // Uniform in the tangent so telephoto and wide angle lenses are equally likely.
+            let (min, max) = ((self.min_fov / 2.0).tan(), (self.max_fov / 2.0).tan());
+            rng.gen_range(min..=max).atan() * 2.0
+        } else {
+            self.min_fov
+        }
+    }
+}
+
+/// Lets the user look around the references with the mouse.
+///
+/// Dragging with the middle mouse button, or the left one while holding Alt, orbits around the
+/// focus. Holding Shift pans instead and holding Ctrl moves the camera closer or farther away.
+/// Scrolling zooms towards the cursor.
+#[derive(Component, Debug, Clone)]
+pub struct OrbitCamera {
+    /// The point the camera orbits around.
+    pub focus: Vec3,
+    /// Limits of the distance between the camera and the focus, for orthographic projections
+    /// relative to the framed distance.
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// The view chosen for the current pose.
+    home: Transform,
+    home_focus: Vec3,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        let home = Transform::from_xyz(0.0, 0.0, BASE_DISTANCE).looking_at(Vec3::ZERO, Vec3::Y);
+        Self {
+            focus: Vec3::ZERO,
+            min_distance: 0.5,
+            max_distance: BASE_DISTANCE * MAX_ZOOM_OUT,
+            home,
+            home_focus: Vec3::ZERO,
+        }
+    }
+}
+
+impl OrbitCamera {
+    fn home_distance(&self) -> f32 {
+        self.home.translation.distance(self.home_focus)
+    }
+
+    /// Rotates the camera around the focus, `delta` is the mouse movement in pixels.
+    fn orbit(&self, transform: &mut Transform, delta: Vec2) {
+        let offset = transform.translation - self.focus;
+        let elevation = (offset.normalize_or_zero().y).clamp(-1.0, 1.0).asin();
+        let new_elevation =
+            (elevation + delta.y * ORBIT_SPEED).clamp(-MAX_ELEVATION, MAX_ELEVATION);
+
+        let yaw = Quat::from_rotation_y(-delta.x * ORBIT_SPEED);
+        // Rotating around the right axis by a positive angle lowers the camera.
+        let pitch = Quat::from_axis_angle(*transform.right(), elevation - new_elevation);
+        transform.rotate_around(self.focus, yaw * pitch);
+    }
+
+    /// Moves the camera and the focus parallel to the view plane by `offset` in world units.
+    fn pan(&mut self, transform: &mut Transform, offset: Vec2) {
+        let offset = *transform.right() * offset.x + *transform.up() * offset.y;
+        transform.translation += offset;
+        self.focus += offset;
+    }
+
+    /// Moves the camera closer to the focus by `factor`, or zooms in for orthographic projections.
+    /// `target` keeps its position on screen.
+    fn dolly(
+        &mut self,
+        transform: &mut Transform,
+        projection: &mut Projection,
+        factor: f32,
+        target: Option<Vec3>,
+    ) {
+        let factor = match projection {
+            Projection::Perspective(_) => {
+                let distance = transform.translation.distance(self.focus);
+                let new_distance = (distance * factor).clamp(self.min_distance, self.max_distance);
+                new_distance / distance.max(f32::EPSILON)
+            }
+            Projection::Orthographic(orthographic) => {
+                let home = self.home_distance();
+                let scale = (orthographic.scale * factor)
+                    .clamp(self.min_distance / home, self.max_distance / home);
+                let factor = scale / orthographic.scale;
+                orthographic.scale = scale;
+                factor
+            }
+        };
+
+        let target = target.unwrap_or(self.focus);
+        let focus = target + (self.focus - target) * factor;
+        match projection {
+            // Scaling the camera position around a point on the view ray keeps that point in place.
+            Projection::Perspective(_) => {
+                transform.translation = target + (transform.translation - target) * factor;
+            }
+            Projection::Orthographic(_) => transform.translation += focus - self.focus,
+        }
+        self.focus = focus;
+    }
+
+    fn reset(&mut self, transform: &mut Transform, projection: &mut Projection) {
+        *transform = self.home;
+        self.focus = self.home_focus;
+        if let Projection::Orthographic(orthographic) = projection {
+            orthographic.scale = 1.0;
+        }
+    }
+}
+
+/// Size of a pixel in world units at the distance of the focus.
+fn world_per_pixel(projection: &Projection, distance: f32, window_height: f32) -> f32 {
+    let height = match projection {
+        Projection::Perspective(perspective) => 2.0 * distance * (perspective.fov / 2.0).tan(),
+        Projection::Orthographic(orthographic) => orthographic.area.height(),
+    };
+    height / window_height.max(1.0)
+}
+
+/// The distance at which the reference appears as large as it does at [`BASE_DISTANCE`] with
+/// [`BASE_FOV`].
+pub fn distance_for_fov(fov: f32) -> f32 {
+    BASE_DISTANCE * (BASE_FOV / 2.0).tan() / (fov / 2.0).tan()
+}
+
+/// The distance at which a sphere with `radius` exactly fits into the view.
+/// `fov` is the vertical field of view and `aspect_ratio` the width divided by the height.
+pub fn distance_to_fit(radius: f32, fov: f32, aspect_ratio: f32) -> f32 {
+    let horizontal_fov = 2.0 * ((fov / 2.0).tan() * aspect_ratio).atan();
+    radius / (fov.min(horizontal_fov) / 2.0).sin()
+}
+
+/// Chooses the lens for a new pose and points the camera at the references.
+///
+/// The references keep the same size on screen while the field of view changes, so a wide angle
+/// lens means a close camera and strong perspective, and a narrow one a far camera and a nearly
+/// flat image.
+fn frame_pose(
+    settings: Res<LensSettings>,
+    mut new_poses: EventReader<NewPose>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut camera: Query<(&mut Transform, &mut Projection, &mut OrbitCamera), With<MainCamera>>,
+) {
+    let Some(pose) = new_poses.read().last() else {
+        return;
+    };
+    let aspect_ratio = windows
+        .get_single()
+        .map_or(1.0, |window| window.width() / window.height().max(1.0));
+    let (center, radius) = if settings.auto_frame {
+        (pose.center, pose.radius * (1.0 + settings.margin))
+    } else {
+        (Vec3::ZERO, BASE_DISTANCE * (BASE_FOV / 2.0).sin())
+    };
+
+    let mut rng = rand::thread_rng();
+    for (mut transform, mut projection, mut orbit) in camera.iter_mut() {
+        let direction = transform.back();
+        let distance = match settings.projection {
+            LensProjection::Perspective => {
+                let fov = settings.sample_fov(&mut rng);
+                *projection = Projection::Perspective(PerspectiveProjection { fov, ..default() });
+                if settings.auto_frame {
+                    distance_to_fit(radius, fov, aspect_ratio)
+                } else {
+                    distance_for_fov(fov)
+                }
+            }
+            LensProjection::Orthographic => {
+                *projection = Projection::Orthographic(OrthographicProjection {
+                    scaling_mode: ScalingMode::AutoMin {
+                        min_width: 2.0 * radius,
+                        min_height: 2.0 * radius,
+                    },
+                    ..default()
+                });
+                // Far enough away to not clip into the references.
+                BASE_DISTANCE.max(radius * 2.0)
+            }
+        };
+        *transform =
+            Transform::from_translation(center + direction * distance).looking_at(center, Vec3::Y);
+
+        *orbit = OrbitCamera {
+            focus: center,
+            // Keeps the camera outside of the bounding sphere of the references.
+            min_distance: pose.radius.min(distance),
+            max_distance: distance * MAX_ZOOM_OUT,
+            home: *transform,
+            home_focus: center,
+        };
+    }
+}
+
+fn orbit_camera(
+    mut contexts: EguiContexts,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut wrapping_cursor: ResMut<NextState<WrappingCursorState>>,
+    mut drag_button: Local<Option<MouseButton>>,
+    mut camera: Query<
+        (
+            &Camera,
+            &GlobalTransform,
+            &mut Transform,
+            &mut Projection,
+            &mut OrbitCamera,
+        ),
+        With<MainCamera>,
+    >,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let ctx = contexts.ctx_mut();
+    let over_ui = ctx.is_pointer_over_area();
+    let alt = keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    match *drag_button {
+        None if !over_ui => {
+            if mouse_buttons.just_pressed(MouseButton::Middle) {
+                *drag_button = Some(MouseButton::Middle);
+            } else if mouse_buttons.just_pressed(MouseButton::Left) && alt {
+                *drag_button = Some(MouseButton::Left);
+            }
+            if drag_button.is_some() {
+                wrapping_cursor.set(WrappingCursorState::On);
+            }
+        }
+        Some(button) if !mouse_buttons.pressed(button) => {
+            *drag_button = None;
+            wrapping_cursor.set(WrappingCursorState::Off);
+        }
+        _ => {}
+    }
+
+    // Raw mouse motion isn't affected by the cursor wrapping around.
+    let delta: Vec2 = mouse_motion.read().map(|e| e.delta).sum();
+    let scroll: f32 = mouse_wheel
+        .read()
+        .map(|e| match e.unit {
+            MouseScrollUnit::Line => e.y,
+            MouseScrollUnit::Pixel => e.y / 100.0,
+        })
+        .sum();
+    let reset = keyboard_input.just_pressed(RESET_VIEW_KEY) && !ctx.wants_keyboard_input();
+
+    for (camera, global_transform, mut transform, mut projection, mut orbit) in camera.iter_mut() {
+        if reset {
+            orbit.reset(&mut transform, &mut projection);
+            continue;
+        }
+
+        if drag_button.is_some() && delta != Vec2::ZERO {
+            if shift {
+                let distance = transform.translation.distance(orbit.focus);
+                let scale = world_per_pixel(&projection, distance, window.height());
+                orbit.pan(&mut transform, Vec2::new(-delta.x, delta.y) * scale);
+            } else if ctrl {
+                let factor = (delta.y * DOLLY_SPEED).exp();
+                orbit.dolly(&mut transform, &mut projection, factor, None);
+            } else {
+                orbit.orbit(&mut transform, delta);
+            }
+        }
+
+        if scroll != 0.0 && !over_ui {
+            // The point under the cursor in the plane through the focus.
+            let target = window
+                .cursor_position()
+                .and_then(|cursor| camera.viewport_to_world(global_transform, cursor))
+                .and_then(|ray| {
+                    let plane = Plane3d::new(*transform.forward());
+                    ray.intersect_plane(orbit.focus, plane)
+                        .map(|distance| ray.get_point(distance))
+                });
+            let factor = (-scroll * ZOOM_SPEED).exp();
+            orbit.dolly(&mut transform, &mut projection, factor, target);
+        }
+    }
+}
+
+#[test]
+fn test_orbit_camera() {
+    let mut orbit = OrbitCamera::default();
+    let mut transform = orbit.home;
+
+    // Orbiting keeps the distance and stops short of looking straight down.
+    orbit.orbit(&mut transform, Vec2::new(100.0, 10_000.0));
+    assert!((transform.translation.length() - BASE_DISTANCE).abs() < 1e-3);
+    assert!(transform.back().y <= MAX_ELEVATION.sin() + 1e-4);
+    assert!(transform.back().y > 0.9);
+
+    // Zooming in keeps the target in the same direction from the camera.
+    let mut projection = Projection::Perspective(default());
+    let target = orbit.focus + *transform.right();
+    let direction = (target - transform.translation).normalize();
+    orbit.dolly(&mut transform, &mut projection, 0.5, Some(target));
+    assert!((transform.translation.distance(orbit.focus) - BASE_DISTANCE * 0.5).abs() < 1e-3);
+    assert!((target - transform.translation).normalize().dot(direction) > 0.9999);
+
+    // The distance is clamped.
+    orbit.dolly(&mut transform, &mut projection, 0.0, None);
+    assert!((transform.translation.distance(orbit.focus) - orbit.min_distance).abs() < 1e-3);
+
+    orbit.reset(&mut transform, &mut projection);
+    assert_eq!(transform, orbit.home);
+}
+
+#[test]
+fn test_distance_to_fit() {
+    // A unit sphere at distance 2 spans 30° in every direction.
+    let fov = 60f32.to_radians();
+    assert!((distance_to_fit(1.0, fov, 1.0) - 2.0).abs() < 1e-5);
+    assert!((distance_to_fit(1.0, fov, 2.0) - 2.0).abs() < 1e-5);
+    // In a portrait window the horizontal field of view is the limit.
+    assert!(distance_to_fit(1.0, fov, 0.5) > 2.5);
+}
+
+#[test]
+fn test_distance_for_fov() {
+    assert!((distance_for_fov(BASE_FOV) - BASE_DISTANCE).abs() < 1e-5);
+    // A narrower field of view needs a farther camera for the same size on screen.
+    assert!(distance_for_fov(20f32.to_radians()) > BASE_DISTANCE * 2.0);
+    assert!(distance_for_fov(90f32.to_radians()) < BASE_DISTANCE / 2.0);
+}
+
+#[test]
+fn test_sample_fov() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut settings = LensSettings {
+        random_fov: true,
+        min_fov: 30f32.to_radians(),
+        max_fov: 30f32.to_radians(),
+        ..default()
+    };
+    assert_eq!(settings.sample_fov(&mut rng), settings.min_fov);
+
+    settings.max_fov = 90f32.to_radians();
+    let fov = settings.sample_fov(&mut rng);
+    assert!(
+        (settings.min_fov..=settings.max_fov).contains(&fov),
+        "{fov}"
+    );
+
+    settings.random_fov = false;
+    assert_eq!(settings.sample_fov(&mut rng), settings.fov);
+}
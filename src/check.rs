@@ -0,0 +1,263 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::references::{EndPose, NewPose, ReferenceSet, References};
+use crate::timer::{PoseTimer, Timer, TimerEvent, TimerSet, UI_RENDER_LAYER};
+use crate::MainCamera;
+
+/// Ends a pose with a check phase in which the references turn around, so the user can compare
+/// their drawing with the form from other sides, before the next references fade in.
+pub struct CheckPlugin;
+
+impl Plugin for CheckPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CheckSettings>()
+            .add_systems(Startup, setup_fade)
+            .add_systems(
+                Update,
+                (
+                    check_pose.after(TimerSet).before(ReferenceSet),
+                    fade_in.after(ReferenceSet),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CheckSettings {
+    pub enabled: bool,
+    pub duration: Duration,
+    pub motion: CheckMotion,
+    /// How long the next references take to fade in after the check.
+    pub fade: Duration,
+}
+
+impl Default for CheckSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            duration: Duration::from_secs(4),
+            motion: CheckMotion::Turn,
+            fade: Duration::from_millis(400),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckMotion {
+    /// A full turn around the up axis.
+    Turn,
+    /// The front, side and top views one after another.
+    Views,
+}
+
+/// The check phase in progress.
+struct Checking {
+    elapsed: Duration,
+    /// How long the pose that is being checked lasted.
+    pose_elapsed: Duration,
+    /// The references with their rotation at the end of the pose.
+    references: Vec<(Entity, Quat)>,
+}
+
+/// Covers the view while the next references fade in.
+#[derive(Component)]
+struct FadeOverlay;
+
+/// Turns the pose timer firing into [`EndPose`], with a check phase in between if enabled.
+/// The pose timer is held during the check, so it doesn't count towards the next pose.
+fn check_pose(
+    settings: Res<CheckSettings>,
+    time: Res<Time>,
+    refs: Res<References>,
+    mut checking: Local<Option<Checking>>,
+    mut timer_events: EventReader<TimerEvent>,
+    mut end_poses: EventWriter<EndPose>,
+    mut timers: Query<&mut Timer, With<PoseTimer>>,
+    mut transforms: Query<&mut Transform>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+) {
+    let timer_event = timer_events
+        .read()
+        .filter(|e| timers.contains(e.timer))
+        .last()
+        .copied();
+
+    if let Some(check) = checking.as_mut() {
+        check.elapsed += time.delta();
+        // Skipping to the next pose also skips the check.
+        if check.elapsed < settings.duration && timer_event.is_none() {
+            let t = check.elapsed.as_secs_f32() / settings.duration.as_secs_f32();
+            let camera_rotation = camera
+                .get_single()
+                .map_or(Quat::IDENTITY, |camera| camera.compute_transform().rotation);
+            for &(entity, start) in check.references.iter() {
+                if let Ok(mut transform) = transforms.get_mut(entity) {
+                    transform.rotation = check_rotation(settings.motion, start, camera_rotation, t);
+                }
+            }
+            return;
+        }
+
+        end_poses.send(EndPose {
+            elapsed: check.pose_elapsed,
+        });
+        *checking = None;
+        for mut timer in timers.iter_mut() {
+            timer.held = false;
+        }
+        return;
+    }
+
+    let Some(timer_event) = timer_event else {
+        return;
+    };
+    if !settings.enabled || refs.shown.is_empty() || settings.duration.is_zero() {
+        end_poses.send(EndPose {
+            elapsed: timer_event.elapsed,
+        });
+        return;
+    }
+
+    *checking = Some(Checking {
+        elapsed: Duration::ZERO,
+        pose_elapsed: timer_event.elapsed,
+        references: refs
+            .shown
+            .iter()
+            .filter_map(|&i| {
+                let entity = refs.references[i].entity;
+                Some((entity, transforms.get(entity).ok()?.rotation))
+            })
+            .collect(),
+    });
+    for mut timer in timers.iter_mut() {
+        timer.held = true;
+    }
+}
+
+/// The rotation of a reference `t` (from 0.0 to 1.0) into the check phase.
+fn check_rotation(motion: CheckMotion, start: Quat, camera_rotation: Quat, t: f32) -> Quat {
+    let ease = |t: f32| t * t * (3.0 - 2.0 * t);
+    match motion {
+        CheckMotion::Turn => Quat::from_rotation_y(TAU * ease(t)) * start,
+        CheckMotion::Views => {
+            // The reference's front, right side and top facing the camera.
+            let views = [
+                camera_rotation,
+                camera_rotation * Quat::from_rotation_y(-FRAC_PI_2),
+                camera_rotation * Quat::from_rotation_x(FRAC_PI_2),
+            ];
+            // Each view is turned to in the first half of its time and held in the second half.
+            let t = t * views.len() as f32;
+            let i = (t as usize).min(views.len() - 1);
+            let from = if i == 0 { start } else { views[i - 1] };
+            let local_t = ((t - i as f32) * 2.0).min(1.0);
+            from.slerp(views[i], ease(local_t))
+        }
+    }
+}
+
+fn setup_fade(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            // Below the rest of the ui.
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+        FadeOverlay,
+        UI_RENDER_LAYER,
+    ));
+}
+
+fn fade_in(
+    settings: Res<CheckSettings>,
+    time: Res<Time>,
+    clear_color: Res<ClearColor>,
+    mut remaining: Local<Duration>,
+    mut new_poses: EventReader<NewPose>,
+    mut overlay: Query<&mut BackgroundColor, With<FadeOverlay>>,
+) {
+    if new_poses.read().count() > 0 && settings.enabled {
+        *remaining = settings.fade;
+    }
+    if remaining.is_zero() {
+        return;
+    }
+
+    *remaining = remaining.saturating_sub(time.delta());
+    let alpha = remaining.as_secs_f32() / settings.fade.as_secs_f32().max(f32::EPSILON);
+    for mut background in overlay.iter_mut() {
+        background.0 = clear_color.0.with_a(alpha);
+    }
+}
+
+#[test]
+fn test_check_rotation() {
+    let start = Quat::from_rotation_z(1.0);
+    let camera = Quat::from_rotation_y(0.5);
+
+    for motion in [CheckMotion::Turn, CheckMotion::Views] {
+        assert!(check_rotation(motion, start, camera, 0.0).angle_between(start) < 1e-3);
+    }
+    assert!(check_rotation(CheckMotion::Turn, start, camera, 1.0).angle_between(start) < 1e-3);
+
+    // At the end the top of the reference faces the camera.
+    let top = check_rotation(CheckMotion::Views, start, camera, 1.0) * Vec3::Y;
+    assert!(top.dot(camera * Vec3::Z) > 0.999);
+}
+
+#[test]
+fn test_pause_during_check() {
+    use crate::references::test_reference;
+
+    let mut app = App::new();
+    app.init_resource::<Time>()
+        .insert_resource(CheckSettings {
+            enabled: true,
+            ..default()
+        })
+        .add_event::<TimerEvent>()
+        .add_event::<EndPose>()
+        .add_systems(Update, check_pose);
+    let entity = app.world.spawn(Transform::default()).id();
+    app.insert_resource(References {
+        references: vec![test_reference("a", entity)],
+        disabled_references: default(),
+        current_reference: Some(0),
+        shown: vec![0],
+        loading_folder: Handle::default(),
+    });
+    let timer = app
+        .world
+        .spawn((Timer::new(Duration::from_secs(60)), PoseTimer))
+        .id();
+
+    app.world.send_event(TimerEvent {
+        timer,
+        elapsed: Duration::from_secs(60),
+    });
+    app.update();
+    assert!(app.world.get::<Timer>(timer).unwrap().held);
+
+    // Pausing during the check isn't undone when it ends.
+    app.world.get_mut::<Timer>(timer).unwrap().set_pause(true);
+    app.world
+        .resource_mut::<Time>()
+        .advance_by(CheckSettings::default().duration);
+    app.update();
+    let ended = app.world.resource::<Events<EndPose>>();
+    assert_eq!(ended.get_reader().read(ended).count(), 1);
+    let timer = app.world.get::<Timer>(timer).unwrap();
+    assert!(!timer.held);
+    assert!(timer.is_paused());
+}
@@ -0,0 +1,303 @@
+use bevy::{
+    core_pipeline::{
+        core_3d::graph::{Core3d, Node3d},
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+        prepass::ViewPrepassTextures,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        extract_component::{
+            ComponentUniforms, ExtractComponent, ExtractComponentPlugin, UniformComponentPlugin,
+        },
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::{
+                texture_2d, texture_2d_multisampled, texture_depth_2d,
+                texture_depth_2d_multisampled, uniform_buffer,
+            },
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        view::{ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms},
+        Render, RenderApp, RenderSet,
+    },
+};
+
+use uniform::EdgeDetectionUniform;
+
+const EDGE_DETECTION_SHADER: &str = "edge_detection.wgsl";
+
+/// Draws outlines in screen space where the depth or the normals of the prepasses change abruptly,
+/// for any camera with [`EdgeDetection`]. Unlike the inverted hull this works for open,
+/// non-manifold and seamed meshes, and finds the intersections between references.
+///
+/// The camera also needs [`DepthPrepass`](bevy::core_pipeline::prepass::DepthPrepass) and
+/// [`NormalPrepass`](bevy::core_pipeline::prepass::NormalPrepass). Only opaque meshes are drawn
+/// into those, so blended ones get no outlines.
+pub struct EdgeDetectionPlugin;
+
+impl Plugin for EdgeDetectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractComponentPlugin::<EdgeDetection>::default(),
+            UniformComponentPlugin::<EdgeDetectionUniform>::default(),
+        ));
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<SpecializedRenderPipelines<EdgeDetectionPipeline>>()
+            .add_systems(
+                Render,
+                prepare_edge_detection_pipelines.in_set(RenderSet::Prepare),
+            )
+            .add_render_graph_node::<ViewNodeRunner<EdgeDetectionNode>>(Core3d, EdgeDetectionLabel)
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::Tonemapping,
+                    EdgeDetectionLabel,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<EdgeDetectionPipeline>();
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct EdgeDetection {
+    pub color: Color,
+    /// In physical pixels.
+    pub width: f32,
+    /// The difference in depth that makes an edge, relative to the depth.
+    pub depth_threshold: f32,
+    /// The difference between the normals that makes an edge, from 0.0 for the same normal to
+    /// 2.0 for opposite ones.
+    pub normal_threshold: f32,
+}
+
+impl Default for EdgeDetection {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            width: 2.0,
+            depth_threshold: 0.05,
+            normal_threshold: 0.4,
+        }
+    }
+}
+
+impl ExtractComponent for EdgeDetection {
+    type QueryData = &'static Self;
+    type QueryFilter = ();
+    type Out = uniform::EdgeDetectionUniform;
+
+    fn extract_component(edge_detection: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(uniform::EdgeDetectionUniform {
+            color: edge_detection.color.as_linear_rgba_f32().into(),
+            width: edge_detection.width,
+            depth_threshold: edge_detection.depth_threshold,
+            normal_threshold: edge_detection.normal_threshold,
+        })
+    }
+}
+
+mod uniform {
+    // The checks generated by deriving `ShaderType` count as dead code.
+    #![allow(dead_code)]
+
+    use bevy::{prelude::*, render::render_resource::ShaderType};
+
+    /// [`super::EdgeDetection`] in the render world.
+    #[derive(Component, Debug, Clone, Copy, ShaderType)]
+    pub struct EdgeDetectionUniform {
+        pub color: Vec4,
+        pub width: f32,
+        pub depth_threshold: f32,
+        pub normal_threshold: f32,
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct EdgeDetectionLabel;
+
+#[derive(Default)]
+struct EdgeDetectionNode;
+
+impl ViewNode for EdgeDetectionNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static ViewPrepassTextures,
+        &'static ViewUniformOffset,
+        &'static EdgeDetectionPipelineId,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, prepass_textures, view_offset, pipeline_id): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let edge_detection_pipeline = world.resource::<EdgeDetectionPipeline>();
+        let multisampled = world.resource::<Msaa>().samples() > 1;
+        let layout = &edge_detection_pipeline.layouts[multisampled as usize];
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline_id.0)
+        else {
+            return Ok(());
+        };
+        let (Some(depth), Some(normal)) = (
+            prepass_textures.depth_view(),
+            prepass_textures.normal_view(),
+        ) else {
+            return Ok(());
+        };
+        let Some(settings) = world
+            .resource::<ComponentUniforms<EdgeDetectionUniform>>()
+            .uniforms()
+            .binding()
+        else {
+            return Ok(());
+        };
+        let Some(view) = world.resource::<ViewUniforms>().uniforms.binding() else {
+            return Ok(());
+        };
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "edge_detection_bind_group",
+            layout,
+            &BindGroupEntries::sequential((post_process.source, depth, normal, settings, view)),
+        );
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("edge_detection_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[view_offset.offset]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct EdgeDetectionPipeline {
+    /// Without and with multisampling.
+    layouts: [BindGroupLayout; 2],
+    shader: Handle<Shader>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct EdgeDetectionPipelineKey {
+    /// The prepass textures are multisampled along with the main pass.
+    multisampled: bool,
+    /// Of the view's main texture, which differs with HDR.
+    format: TextureFormat,
+}
+
+/// The pipeline specialized for a view.
+#[derive(Component)]
+struct EdgeDetectionPipelineId(CachedRenderPipelineId);
+
+impl FromWorld for EdgeDetectionPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layouts = [false, true].map(|multisampled| {
+            let float = TextureSampleType::Float { filterable: false };
+            let (depth, normal) = if multisampled {
+                (
+                    texture_depth_2d_multisampled(),
+                    texture_2d_multisampled(float),
+                )
+            } else {
+                (texture_depth_2d(), texture_2d(float))
+            };
+            world.resource::<RenderDevice>().create_bind_group_layout(
+                "edge_detection_bind_group_layout",
+                &BindGroupLayoutEntries::sequential(
+                    ShaderStages::FRAGMENT,
+                    (
+                        texture_2d(float),
+                        depth,
+                        normal,
+                        uniform_buffer::<EdgeDetectionUniform>(false),
+                        uniform_buffer::<ViewUniform>(true),
+                    ),
+                ),
+            )
+        });
+
+        Self {
+            layouts,
+            shader: world.resource::<AssetServer>().load(EDGE_DETECTION_SHADER),
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for EdgeDetectionPipeline {
+    type Key = EdgeDetectionPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("edge_detection_pipeline".into()),
+            layout: vec![self.layouts[key.multisampled as usize].clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: self.shader.clone(),
+                shader_defs: if key.multisampled {
+                    vec!["MULTISAMPLED".into()]
+                } else {
+                    vec![]
+                },
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: key.format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: vec![],
+        }
+    }
+}
+
+fn prepare_edge_detection_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<EdgeDetectionPipeline>>,
+    edge_detection_pipeline: Res<EdgeDetectionPipeline>,
+    msaa: Res<Msaa>,
+    views: Query<(Entity, &ViewTarget), With<EdgeDetectionUniform>>,
+) {
+    for (entity, view_target) in views.iter() {
+        let key = EdgeDetectionPipelineKey {
+            multisampled: msaa.samples() > 1,
+            format: view_target.main_texture_format(),
+        };
+        let id = pipelines.specialize(&pipeline_cache, &edge_detection_pipeline, key);
+        commands.entity(entity).insert(EdgeDetectionPipelineId(id));
+    }
+}
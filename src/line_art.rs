@@ -0,0 +1,515 @@
+use bevy::{
+    core_pipeline::prepass::{DepthPrepass, NormalPrepass},
+    prelude::*,
+    utils::HashMap,
+    window::PrimaryWindow,
+};
+
+use crate::camera::OrbitCamera;
+use crate::edge_detection::EdgeDetection;
+use crate::outline::OutlineMaterial;
+use crate::references::{EdgeKind, LineArtGizmo, References};
+use crate::MainCamera;
+
+/// Thickness of the outlines and lines in world units. References are normalized to a radius of
+/// about 1.0.
+pub const DEFAULT_THICKNESS: f32 = 0.02;
+
+/// Opacity of the references in the x-ray view.
+const XRAY_ALPHA: f32 = 0.15;
+const XRAY_EDGE_COLOR: Color = Color::rgb(0.45, 0.45, 0.45);
+/// Opacity of faint hidden edges relative to their visible color.
+const HIDDEN_EDGE_ALPHA: f32 = 0.35;
+/// Length of the dashes and the gaps between them relative to the distance to the camera.
+const DASH_LENGTH: f32 = 0.012;
+/// The suggestive contours are found again once the eye moved this far from where they were found,
+/// relative to the reference, or turned this many radians.
+const EYE_TOLERANCE: f32 = 1e-3;
+
+/// Draws the line art of the shown references in the selected style.
+pub struct LineArtPlugin;
+
+impl Plugin for LineArtPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LineArtSettings>()
+            .init_gizmo_group::<HiddenLineGizmo>()
+            .add_systems(
+                Update,
+                (apply_line_art_style, update_thickness, draw_line_art),
+            );
+    }
+}
+
+/// The lines once more on top of the references, where they are hidden. Elsewhere the depth
+/// tested [`LineArtGizmo`] lines cover them in the same color.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct HiddenLineGizmo;
+
+#[derive(Resource, Debug, Clone)]
+pub struct LineArtSettings {
+    pub style: LineArtStyle,
+    /// How creases behind the references are drawn. The x-ray view always shows them.
+    pub hidden_edges: HiddenEdges,
+    /// Used for every reference without its own crease angles.
+    pub crease_angles: CreaseAngles,
+    /// Draw the rims of open surfaces, like a plane or a cylinder without caps.
+    pub boundaries: bool,
+    pub boundary_color: Color,
+    /// How the silhouette is drawn in the styles showing it.
+    pub outline: OutlineMethod,
+    /// Thickness of the lines in world units.
+    pub thickness: f32,
+    /// Keep the lines the same width on screen however far away the references are, instead of
+    /// the same thickness in the world.
+    pub constant_width: bool,
+    /// Width of the lines in logical pixels with `constant_width`.
+    pub width: f32,
+    /// Feature lines following the curvature, for organic forms without creases.
+    pub suggestive_contours: bool,
+    pub ridges_and_valleys: bool,
+    pub ridge_color: Color,
+}
+
+impl Default for LineArtSettings {
+    fn default() -> Self {
+        Self {
+            style: default(),
+            hidden_edges: default(),
+            crease_angles: default(),
+            boundaries: true,
+            boundary_color: Color::WHITE,
+            outline: default(),
+            thickness: DEFAULT_THICKNESS,
+            constant_width: false,
+            width: 3.0,
+            suggestive_contours: false,
+            ridges_and_valleys: false,
+            ridge_color: Color::WHITE,
+        }
+    }
+}
+
+/// Edges whose faces meet at an angle between `min` and `max` (in radians) are drawn as creases.
+/// Faces meeting at 180° are flat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CreaseAngles {
+    /// Ignore the angles and draw every edge that isn't part of a smooth surface, so curved
+    /// surfaces don't show their facets however finely they are tessellated. `min` and `max` are
+    /// kept for when it's turned off.
+    pub adaptive: bool,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for CreaseAngles {
+    fn default() -> Self {
+        Self {
+            adaptive: true,
+            min: 45f32.to_radians(),
+            max: 135f32.to_radians(),
+        }
+    }
+}
+
+impl CreaseAngles {
+    /// Whether an edge with the given angle between its faces is drawn.
+    pub fn contains(&self, angle: f32, smooth: bool) -> bool {
+        if self.adaptive {
+            !smooth
+        } else {
+            self.min < angle && angle < self.max
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HiddenEdges {
+    /// Not at all.
+    #[default]
+    Hidden,
+    /// Dashed, like in a technical drawing.
+    Dashed,
+    /// Solid but faint.
+    Faint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutlineMethod {
+    /// An inverted copy of the meshes behind them, cheap but blobby on smooth shapes.
+    #[default]
+    Hull,
+    /// The edges between faces turned towards the camera and faces turned away, found for the
+    /// current view every frame and drawn like the creases.
+    Contours,
+    /// Found in screen space where the depth or the normals change abruptly, which also works for
+    /// open and non-manifold meshes and shows where references intersect. Only opaque meshes are
+    /// outlined, not references with blended materials, the ground or the grid.
+    EdgeDetection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineArtStyle {
+    /// Only the silhouette.
+    Outline,
+    /// Only the sharp edges.
+    Creases,
+    #[default]
+    OutlineAndCreases,
+    /// The shaded references without any lines.
+    Shaded,
+    /// Translucent references with all edges visible, like a construction drawing.
+    XRay,
+}
+
+impl LineArtStyle {
+    pub const ALL: [LineArtStyle; 5] = [
+        LineArtStyle::Outline,
+        LineArtStyle::Creases,
+        LineArtStyle::OutlineAndCreases,
+        LineArtStyle::Shaded,
+        LineArtStyle::XRay,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineArtStyle::Outline => "Outline",
+            LineArtStyle::Creases => "Creases",
+            LineArtStyle::OutlineAndCreases => "Outline and creases",
+            LineArtStyle::Shaded => "Shaded",
+            LineArtStyle::XRay => "X-ray",
+        }
+    }
+
+    pub fn shows_outline(self) -> bool {
+        matches!(
+            self,
+            LineArtStyle::Outline | LineArtStyle::OutlineAndCreases
+        )
+    }
+
+    /// Whether creases and boundaries are drawn with gizmos.
+    pub fn shows_edges(self) -> bool {
+        self != LineArtStyle::Shaded
+    }
+
+    pub fn shows_creases(self) -> bool {
+        matches!(
+            self,
+            LineArtStyle::Creases | LineArtStyle::OutlineAndCreases | LineArtStyle::XRay
+        )
+    }
+}
+
+/// Updates the outline visibility, the reference materials and the gizmo config whenever the
+/// style changes or new references are spawned.
+fn apply_line_art_style(
+    settings: Res<LineArtSettings>,
+    refs: Res<References>,
+    mut config_store: ResMut<GizmoConfigStore>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    added_outlines: Query<(), Added<Handle<OutlineMaterial>>>,
+    mut outlines: Query<&mut Visibility, With<Handle<OutlineMaterial>>>,
+    mut applied: Local<Option<(LineArtStyle, HiddenEdges, OutlineMethod)>>,
+) {
+    // The settings are marked as changed by the ui every frame, so compare them instead.
+    let style = settings.style;
+    let current = Some((style, settings.hidden_edges, settings.outline));
+    if *applied == current && added_outlines.is_empty() {
+        return;
+    }
+    *applied = current;
+
+    for mut visibility in outlines.iter_mut() {
+        *visibility = if style.shows_outline() && settings.outline == OutlineMethod::Hull {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    for reference_material in refs.references.iter().flat_map(|r| r.materials.iter()) {
+        let Some(material) = materials.get_mut(&reference_material.handle) else {
+            continue;
+        };
+        if style == LineArtStyle::XRay {
+            material.base_color.set_a(XRAY_ALPHA);
+            material.alpha_mode = AlphaMode::Blend;
+        } else {
+            material.base_color.set_a(reference_material.alpha);
+            material.alpha_mode = reference_material.alpha_mode;
+        }
+    }
+
+    // In the x-ray view all lines are drawn on top of the references.
+    let (config, _) = config_store.config_mut::<LineArtGizmo>();
+    config.depth_bias = if style == LineArtStyle::XRay {
+        -1.0
+    } else {
+        0.0
+    };
+    let (config, _) = config_store.config_mut::<HiddenLineGizmo>();
+    config.depth_bias = -1.0;
+}
+
+/// Sets the thickness of the hull outlines and sizes the gizmo lines and the edge detection to
+/// match them at any window resolution.
+fn update_thickness(
+    mut commands: Commands,
+    settings: Res<LineArtSettings>,
+    refs: Res<References>,
+    mut outline_materials: ResMut<Assets<OutlineMaterial>>,
+    mut config_store: ResMut<GizmoConfigStore>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut camera: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &Projection,
+            &OrbitCamera,
+            Option<&mut EdgeDetection>,
+        ),
+        With<MainCamera>,
+    >,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    // Gizmo line widths are in physical pixels. With perspective they shrink with the distance
+    // like the outlines do, matching them for a vertical field of view of about 53°.
+    let (thickness, constant_width) = if settings.constant_width {
+        (settings.width * window.scale_factor(), 1)
+    } else {
+        (settings.thickness, 0)
+    };
+    let line_perspective = !settings.constant_width;
+    let line_width = if settings.constant_width {
+        thickness
+    } else {
+        thickness * window.physical_height() as f32
+    };
+    let (config, _) = config_store.config_mut::<LineArtGizmo>();
+    config.line_perspective = line_perspective;
+    config.line_width = line_width;
+    let (config, _) = config_store.config_mut::<HiddenLineGizmo>();
+    config.line_perspective = line_perspective;
+    config.line_width = line_width;
+
+    for (entity, transform, projection, orbit, edge_detection) in camera.iter_mut() {
+        if !settings.style.shows_outline() || settings.outline != OutlineMethod::EdgeDetection {
+            if edge_detection.is_some() {
+                commands
+                    .entity(entity)
+                    .remove::<(EdgeDetection, DepthPrepass, NormalPrepass)>();
+            }
+            continue;
+        }
+        // As wide as the gizmo lines at the focus.
+        let width = match projection {
+            Projection::Perspective(_) if line_perspective => {
+                line_width / transform.translation().distance(orbit.focus)
+            }
+            _ => line_width,
+        };
+        match edge_detection {
+            Some(mut edge_detection) => edge_detection.width = width,
+            None => {
+                commands.entity(entity).insert((
+                    EdgeDetection { width, ..default() },
+                    DepthPrepass,
+                    NormalPrepass,
+                ));
+            }
+        }
+    }
+
+    for reference in refs.references.iter() {
+        // Only touched when changed, as that uploads the material again.
+        let Some(material) = outline_materials.get(&reference.outline) else {
+            continue;
+        };
+        if material.thickness != thickness || material.constant_width != constant_width {
+            let material = outline_materials.get_mut(&reference.outline).unwrap();
+            material.thickness = thickness;
+            material.constant_width = constant_width;
+        }
+    }
+}
+
+fn draw_line_art(
+    mut gizmo: Gizmos<LineArtGizmo>,
+    mut hidden_gizmo: Gizmos<HiddenLineGizmo>,
+    settings: Res<LineArtSettings>,
+    refs: Res<References>,
+    transforms: Query<&Transform>,
+    camera: Query<(&GlobalTransform, &Projection), With<MainCamera>>,
+    // By reference, with the eye they were found for, as finding them goes over every triangle.
+    mut suggestive_contours: Local<HashMap<usize, (Eye, Vec<(Vec3, Vec3)>)>>,
+) {
+    let hidden_lines = settings.style != LineArtStyle::XRay
+        && settings.hidden_edges != HiddenEdges::Hidden
+        && settings.style.shows_edges();
+    let eye = camera.get_single().ok().map(|(transform, projection)| Eye {
+        position: transform.translation(),
+        forward: transform.forward(),
+        orthographic: matches!(projection, Projection::Orthographic(_)),
+    });
+    suggestive_contours.retain(|i, _| settings.suggestive_contours && refs.shown.contains(i));
+
+    for &shown in refs.shown.iter() {
+        let reference = &refs.references[shown];
+        let Ok(transform) = transforms.get(reference.entity) else {
+            continue;
+        };
+
+        if settings.style == LineArtStyle::XRay {
+            for (a, b) in reference.wireframe() {
+                gizmo.line(*transform * a, *transform * b, XRAY_EDGE_COLOR);
+            }
+        }
+
+        let mut lines = Vec::new();
+        if let (true, OutlineMethod::Contours, Some(eye)) =
+            (settings.style.shows_outline(), settings.outline, eye)
+        {
+            let contours = contours(&reference.edges, eye.local(transform));
+            lines.extend(contours.map(|(a, b)| (a, b, Color::WHITE)));
+        }
+        if settings.style.shows_creases() {
+            let creases = reference.creases(&settings.crease_angles);
+            lines.extend(creases.map(|(a, b)| (a, b, Color::WHITE)));
+        }
+        if settings.style.shows_edges() {
+            if let (true, Some(eye)) = (settings.suggestive_contours, eye) {
+                let eye = eye.local(transform);
+                let cached = suggestive_contours.get(&shown);
+                if !cached.is_some_and(|(cached, _)| cached.close_to(&eye)) {
+                    let found = reference
+                        .surface
+                        .suggestive_contours(|p| eye.towards_eye(p));
+                    suggestive_contours.insert(shown, (eye, found));
+                }
+                let (_, found) = &suggestive_contours[&shown];
+                lines.extend(found.iter().map(|&(a, b)| (a, b, Color::WHITE)));
+            }
+            if settings.ridges_and_valleys {
+                let ridges_and_valleys = reference.surface.ridges_and_valleys.iter();
+                lines.extend(ridges_and_valleys.map(|&(a, b)| (a, b, settings.ridge_color)));
+            }
+        }
+        if settings.boundaries && settings.style.shows_edges() {
+            let boundaries = reference.boundaries();
+            lines.extend(boundaries.map(|(a, b)| (a, b, settings.boundary_color)));
+        }
+
+        for (a, b, color) in lines {
+            let (a, b) = (*transform * a, *transform * b);
+            gizmo.line(a, b, color);
+            let (true, Some(eye)) = (hidden_lines, eye) else {
+                continue;
+            };
+            let hidden_color = color.with_a(color.a() * HIDDEN_EDGE_ALPHA);
+            match settings.hidden_edges {
+                HiddenEdges::Faint => hidden_gizmo.line(a, b, hidden_color),
+                _ => draw_dashed(&mut hidden_gizmo, a, b, eye.position, hidden_color),
+            }
+        }
+    }
+}
+
+fn draw_dashed(gizmo: &mut Gizmos<HiddenLineGizmo>, from: Vec3, to: Vec3, eye: Vec3, color: Color) {
+    let dash = DASH_LENGTH * eye.distance(from.lerp(to, 0.5));
+    let length = from.distance(to);
+    let direction = (to - from).normalize_or_zero();
+    let mut s = 0.0;
+    while s < length {
+        let end = (s + dash).min(length);
+        gizmo.line(from + direction * s, from + direction * end, color);
+        s += 2.0 * dash;
+    }
+}
+
+/// Where the references are seen from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Eye {
+    position: Vec3,
+    forward: Vec3,
+    orthographic: bool,
+}
+
+impl Eye {
+    /// The eye in the local space of `transform`.
+    fn local(&self, transform: &Transform) -> Eye {
+        let inverse = transform.compute_affine().inverse();
+        Eye {
+            position: inverse.transform_point3(self.position),
+            forward: inverse.transform_vector3(self.forward),
+            orthographic: self.orthographic,
+        }
+    }
+
+    /// Whether the eyes are about the same, so the lines seen from one can be drawn for the other.
+    fn close_to(&self, other: &Eye) -> bool {
+        self.orthographic == other.orthographic
+            && self.position.distance(other.position) < EYE_TOLERANCE
+            && self.forward.angle_between(other.forward) < EYE_TOLERANCE
+    }
+
+    /// The direction from `point` towards the eye, not normalized.
+    fn towards_eye(&self, point: Vec3) -> Vec3 {
+        if self.orthographic {
+            -self.forward
+        } else {
+            self.position - point
+        }
+    }
+
+    /// Whether a face with the `normal` through `point` is turned towards the eye.
+    fn faces(&self, normal: Vec3, point: Vec3) -> bool {
+        normal.dot(self.towards_eye(point)) > 0.0
+    }
+}
+
+/// The edges between a face turned towards the eye and one turned away from it, the silhouette
+/// and the inner contours of a smooth surface as seen from the eye.
+fn contours(edges: &[(Vec3, Vec3, EdgeKind)], eye: Eye) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+    edges.iter().filter_map(move |&(a, b, kind)| match kind {
+        EdgeKind::Manifold {
+            normals: [n0, n1], ..
+        } if eye.faces(n0, a) != eye.faces(n1, a) => Some((a, b)),
+        _ => None,
+    })
+}
+
+#[test]
+fn test_contours() {
+    use std::f32::consts::FRAC_PI_2;
+
+    // A ridge along the x axis, with one face turned up and towards +z and the other away from it.
+    let normals = [Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, 1.0, -1.0)].map(Vec3::normalize);
+    let edges = [(
+        Vec3::NEG_X,
+        Vec3::X,
+        EdgeKind::Manifold {
+            angle: FRAC_PI_2,
+            smooth: false,
+            normals,
+        },
+    )];
+    let front = Eye {
+        position: Vec3::new(0.0, 0.0, 5.0),
+        forward: Vec3::NEG_Z,
+        orthographic: false,
+    };
+    let above = Eye {
+        position: Vec3::new(0.0, 5.0, 0.0),
+        forward: Vec3::NEG_Y,
+        orthographic: true,
+    };
+    assert_eq!(contours(&edges, front).count(), 1);
+    assert_eq!(contours(&edges, above).count(), 0);
+
+    // Turning the ridge towards the front makes both faces visible.
+    let transform = Transform::from_rotation(Quat::from_rotation_x(FRAC_PI_2));
+    assert_eq!(contours(&edges, front.local(&transform)).count(), 0);
+}
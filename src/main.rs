@@ -5,10 +5,11 @@ use std::{
     time::{Duration, Instant},
 };
 
+use arrangement::{ArrangementSettings, Layout};
+use auto_pause::{AutoPause, AutoPausePlugin};
 use bevy::{
     app::AppExit,
     diagnostic::{EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
-    input::mouse::MouseWheel,
     prelude::*,
     render::{
         render_resource::WgpuFeatures,
@@ -25,14 +26,37 @@ use bevy_egui::{
 };
 use bevy_infinite_grid::InfiniteGridPlugin;
 use bevy_mod_picking::prelude::*;
+use camera::{LensProjection, LensSettings, MainCameraPlugin, OrbitCamera};
+use check::{CheckMotion, CheckPlugin, CheckSettings};
+use edge_detection::EdgeDetectionPlugin;
+use line_art::{
+    CreaseAngles, HiddenEdges, LineArtPlugin, LineArtSettings, LineArtStyle, OutlineMethod,
+};
+use outline::OutlinePlugin;
 use picking_ext::{PickingExtPlugin, PointerEvent};
 use rand::Rng;
-use references::{LineArtGizmo, ReferencePlugin, References};
+use references::{LineArtGizmo, ReferencePlugin, References, ScaleSettings};
+use rotation::{Horizon, RotationConstraints, RotationMode, RotationSettings, UpAxis};
+use session::Session;
+use timer::{
+    IntervalSettings, NextPose, PoseTimer, TimeDisplay, Timer, TimerDisplay, TimerMode, TimerPlugin,
+};
+use turntable::{TurntableAxis, TurntablePlugin, TurntableSettings};
 use wrapping_cursor::{Wrap, WrappingCursorPlugin, WrappingCursorState};
 
+mod arrangement;
+mod auto_pause;
+mod camera;
+mod check;
+mod edge_detection;
+mod line_art;
 mod outline;
 mod picking_ext;
 mod references;
+mod rotation;
+mod session;
+mod timer;
+mod turntable;
 mod wrapping_cursor;
 
 fn main() {
@@ -71,13 +95,28 @@ fn main() {
                 .disable::<DefaultHighlightingPlugin>(),
             InfiniteGridPlugin,
         ))
-        .add_plugins((ReferencePlugin, PickingExtPlugin, WrappingCursorPlugin))
+        .add_plugins((
+            ReferencePlugin,
+            TimerPlugin,
+            AutoPausePlugin,
+            MainCameraPlugin,
+            CheckPlugin,
+            EdgeDetectionPlugin,
+            LineArtPlugin,
+            OutlinePlugin,
+            TurntablePlugin,
+            PickingExtPlugin,
+            WrappingCursorPlugin,
+        ))
         .add_systems(Startup, setup)
         .add_systems(
             Update,
             (
-                zoom,
                 ui_active_references,
+                ui_timer,
+                ui_session,
+                ui_pose,
+                next_pose_on_key,
                 close_on_esc,
                 // change_transparency_mode,
             ),
@@ -131,6 +170,500 @@ fn ui_active_references(mut contexts: EguiContexts, mut refs: ResMut<References>
     });
 }
 
+fn ui_timer(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<IntervalSettings>,
+    mut display: ResMut<TimerDisplay>,
+    mut auto_pause: ResMut<AutoPause>,
+    mut next_pose: EventWriter<NextPose>,
+    mut timers: Query<&mut Timer, With<PoseTimer>>,
+) {
+    let Ok(mut timer) = timers.get_single_mut() else {
+        return;
+    };
+
+    egui::Window::new("Timer").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut timer.mode, TimerMode::Interval, "Interval");
+            ui.radio_value(&mut timer.mode, TimerMode::Stopwatch, "Stopwatch");
+            ui.radio_value(&mut timer.mode, TimerMode::Untimed, "Untimed");
+            if ui.button("Next").on_hover_text("Right arrow").clicked() {
+                next_pose.send(NextPose);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Interval");
+            let mut seconds = timer.interval().as_secs_f64();
+            let response = ui.add_enabled(
+                timer.mode == TimerMode::Interval && !settings.random,
+                interval_drag_value(&mut seconds),
+            );
+            if response.changed() {
+                timer.set_interval(Duration::from_secs_f64(seconds));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            for (label, preset) in IntervalSettings::PRESETS {
+                let selected = match preset {
+                    Some(interval) => {
+                        timer.mode == TimerMode::Interval
+                            && !settings.random
+                            && timer.interval() == interval
+                    }
+                    None => timer.mode == TimerMode::Untimed,
+                };
+                if ui.selectable_label(selected, label).clicked() {
+                    settings.random = false;
+                    match preset {
+                        Some(interval) => {
+                            timer.mode = TimerMode::Interval;
+                            timer.set_interval(interval);
+                        }
+                        None => timer.mode = TimerMode::Untimed,
+                    }
+                }
+            }
+        });
+
+        ui.separator();
+        if ui
+            .checkbox(&mut settings.random, "Random interval per pose")
+            .changed()
+            && settings.random
+        {
+            timer.mode = TimerMode::Interval;
+            timer.set_interval(settings.sample(&mut rand::thread_rng()));
+        }
+        ui.add_enabled_ui(settings.random, |ui| {
+            ui.horizontal(|ui| {
+                let mut min = settings.min.as_secs_f64();
+                let mut max = settings.max.as_secs_f64();
+                ui.label("Min");
+                if ui.add(interval_drag_value(&mut min)).changed() {
+                    settings.min = Duration::from_secs_f64(min);
+                    settings.max = settings.max.max(settings.min);
+                }
+                ui.label("Max");
+                if ui.add(interval_drag_value(&mut max)).changed() {
+                    settings.max = Duration::from_secs_f64(max);
+                    settings.min = settings.min.min(settings.max);
+                }
+            });
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Show");
+            ui.radio_value(&mut display.time, TimeDisplay::Remaining, "Remaining");
+            ui.radio_value(&mut display.time, TimeDisplay::Elapsed, "Elapsed");
+        });
+        ui.horizontal(|ui| {
+            let mut warning = display.warning.as_secs_f64();
+            ui.label("Warning");
+            let response = ui.add(
+                egui::DragValue::new(&mut warning)
+                    .speed(0.5)
+                    .clamp_range(0.0..=60.0 * 60.0)
+                    .suffix("s"),
+            );
+            if response.changed() {
+                display.warning = Duration::from_secs_f64(warning);
+            }
+        })
+        .response
+        .on_hover_text(
+            "Time before the end of a pose at which the timer turns red. 0 disables it.",
+        );
+
+        ui.separator();
+        ui.checkbox(&mut auto_pause.on_focus_loss, "Pause when unfocused");
+        ui.horizontal(|ui| {
+            let mut enabled = auto_pause.idle.is_some();
+            let mut idle = auto_pause
+                .idle
+                .unwrap_or(Duration::from_secs(60))
+                .as_secs_f64();
+            let checkbox = ui.checkbox(&mut enabled, "Pause when idle for");
+            let drag = ui.add_enabled(enabled, interval_drag_value(&mut idle));
+            if checkbox.changed() || drag.changed() {
+                auto_pause.idle = enabled.then(|| Duration::from_secs_f64(idle));
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut countdown = auto_pause.resume_countdown.as_secs_f64();
+            ui.label("Resume countdown");
+            let response = ui.add(
+                egui::DragValue::new(&mut countdown)
+                    .speed(0.1)
+                    .clamp_range(0.0..=10.0)
+                    .suffix("s"),
+            );
+            if response.changed() {
+                auto_pause.resume_countdown = Duration::from_secs_f64(countdown);
+            }
+        });
+    });
+}
+
+fn ui_pose(
+    mut contexts: EguiContexts,
+    mut rotation: ResMut<RotationSettings>,
+    mut scale: ResMut<ScaleSettings>,
+    mut lens: ResMut<LensSettings>,
+    mut arrangement: ResMut<ArrangementSettings>,
+    mut turntable: ResMut<TurntableSettings>,
+    mut check: ResMut<CheckSettings>,
+    mut line_art: ResMut<LineArtSettings>,
+    mut refs: ResMut<References>,
+) {
+    egui::Window::new("Pose").show(contexts.ctx_mut(), |ui| {
+        egui::ComboBox::from_label("Line art")
+            .selected_text(line_art.style.name())
+            .show_ui(ui, |ui| {
+                for style in LineArtStyle::ALL {
+                    ui.selectable_value(&mut line_art.style, style, style.name());
+                }
+            });
+        let shows_hidden_edges =
+            line_art.style.shows_edges() && line_art.style != LineArtStyle::XRay;
+        ui.add_enabled_ui(line_art.style.shows_outline(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Outline");
+                ui.radio_value(&mut line_art.outline, OutlineMethod::Hull, "Hull")
+                    .on_hover_text("A slightly larger copy of the reference behind it.");
+                ui.radio_value(&mut line_art.outline, OutlineMethod::Contours, "Contours")
+                    .on_hover_text("Lines where the surface turns away, following the view.");
+                ui.radio_value(
+                    &mut line_art.outline,
+                    OutlineMethod::EdgeDetection,
+                    "Screen space",
+                )
+                .on_hover_text(
+                    "Lines where the depth or the surface direction jumps on screen. \
+                    Works for any mesh and shows where references intersect.",
+                );
+            });
+        });
+        ui.add_enabled_ui(line_art.style.shows_creases(), |ui| {
+            crease_angles_ui(ui, &mut line_art.crease_angles);
+        });
+        ui.add_enabled_ui(line_art.style.shows_edges(), |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut line_art.boundaries, "Open edges")
+                    .on_hover_text("The rims of open surfaces, like a plane.");
+                let [r, g, b, _] = line_art.boundary_color.as_rgba_f32();
+                let mut rgb = [r, g, b];
+                if ui.color_edit_button_rgb(&mut rgb).changed() {
+                    line_art.boundary_color = Color::rgb(rgb[0], rgb[1], rgb[2]);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut line_art.suggestive_contours, "Suggestive contours")
+                    .on_hover_text(
+                        "Where the surface almost turns away, continuing the contours. \
+                        For organic forms like sculpts and scans.",
+                    );
+                ui.checkbox(&mut line_art.ridges_and_valleys, "Ridges and valleys")
+                    .on_hover_text("Where the surface bends most sharply.");
+                let [r, g, b, _] = line_art.ridge_color.as_rgba_f32();
+                let mut rgb = [r, g, b];
+                if ui.color_edit_button_rgb(&mut rgb).changed() {
+                    line_art.ridge_color = Color::rgb(rgb[0], rgb[1], rgb[2]);
+                }
+            });
+        });
+        ui.add_enabled_ui(line_art.style != LineArtStyle::Shaded, |ui| {
+            ui.horizontal(|ui| {
+                if line_art.constant_width {
+                    ui.add(
+                        egui::Slider::new(&mut line_art.width, 0.5..=10.0)
+                            .text("Line width")
+                            .suffix(" px"),
+                    );
+                } else {
+                    ui.add(
+                        egui::Slider::new(&mut line_art.thickness, 0.002..=0.1)
+                            .logarithmic(true)
+                            .text("Line thickness"),
+                    );
+                }
+                ui.checkbox(&mut line_art.constant_width, "Constant on screen")
+                    .on_hover_text("Keep the width when zooming instead of the thickness.");
+            });
+        });
+        ui.add_enabled_ui(shows_hidden_edges, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Hidden edges");
+                ui.radio_value(&mut line_art.hidden_edges, HiddenEdges::Hidden, "Hidden");
+                ui.radio_value(&mut line_art.hidden_edges, HiddenEdges::Dashed, "Dashed");
+                ui.radio_value(&mut line_art.hidden_edges, HiddenEdges::Faint, "Faint");
+            });
+        });
+
+        ui.separator();
+        ui.add(egui::Slider::new(&mut rotation.strictness, 0.0..=1.0).text("View strictness"))
+            .on_hover_text(
+                "How strongly views showing the reference head-on or edge-on are avoided.",
+            );
+
+        ui.separator();
+        rotation_constraints_ui(ui, "global", &mut rotation.constraints);
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut scale.enabled, "Random scale");
+            ui.add_enabled(
+                scale.enabled,
+                egui::Checkbox::new(&mut scale.uniform, "Uniform"),
+            );
+        });
+        ui.add_enabled_ui(scale.enabled, |ui| {
+            let max = scale.max;
+            ui.add(
+                egui::Slider::new(&mut scale.min, 0.1..=max)
+                    .logarithmic(true)
+                    .text("Min scale"),
+            );
+            let min = scale.min;
+            ui.add(
+                egui::Slider::new(&mut scale.max, min..=10.0)
+                    .logarithmic(true)
+                    .text("Max scale"),
+            );
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut lens.projection,
+                LensProjection::Perspective,
+                "Perspective",
+            );
+            ui.radio_value(
+                &mut lens.projection,
+                LensProjection::Orthographic,
+                "Orthographic",
+            );
+        });
+        ui.add_enabled_ui(lens.projection == LensProjection::Perspective, |ui| {
+            ui.checkbox(&mut lens.random_fov, "Random field of view");
+            ui.horizontal(|ui| {
+                if lens.random_fov {
+                    ui.label("Min");
+                    ui.drag_angle(&mut lens.min_fov);
+                    ui.label("Max");
+                    ui.drag_angle(&mut lens.max_fov);
+                } else {
+                    ui.label("Field of view");
+                    ui.drag_angle(&mut lens.fov);
+                }
+            });
+            lens.fov = lens.fov.clamp(MIN_FOV, MAX_FOV);
+            lens.min_fov = lens.min_fov.clamp(MIN_FOV, MAX_FOV);
+            lens.max_fov = lens.max_fov.clamp(lens.min_fov, MAX_FOV);
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut lens.auto_frame, "Frame references")
+                .on_hover_text("Move the camera so the references fill the view.");
+            ui.add_enabled(
+                lens.auto_frame,
+                egui::Slider::new(&mut lens.margin, 0.0..=1.0).text("Margin"),
+            );
+        });
+        ui.weak(
+            "Middle or Alt + drag to orbit, with Shift to pan, with Ctrl to zoom, Home to reset",
+        );
+
+        ui.separator();
+        ui.add(egui::Slider::new(&mut arrangement.count, 1..=6).text("References at once"));
+        ui.add_enabled_ui(arrangement.count > 1, |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut arrangement.layout, Layout::Scattered, "Scattered");
+                ui.radio_value(&mut arrangement.layout, Layout::Ground, "On the ground");
+            });
+            ui.checkbox(&mut arrangement.allow_intersections, "Allow intersections");
+        });
+
+        ui.separator();
+        ui.checkbox(&mut turntable.enabled, "Turntable");
+        ui.add_enabled_ui(turntable.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Axis");
+                ui.radio_value(&mut turntable.axis, TurntableAxis::Up, "Up");
+                ui.radio_value(&mut turntable.axis, TurntableAxis::Horizontal, "Horizontal");
+                ui.radio_value(&mut turntable.axis, TurntableAxis::Random, "Random");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Speed");
+                ui.drag_angle(&mut turntable.speed);
+                ui.label("per second");
+            });
+            ui.checkbox(
+                &mut turntable.stop_at_end,
+                "Stop at the end of the interval",
+            );
+        });
+
+        ui.separator();
+        ui.checkbox(&mut check.enabled, "Check before the next pose")
+            .on_hover_text("Turn the references around at the end of a pose to check the drawing.");
+        ui.add_enabled_ui(check.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut check.motion, CheckMotion::Turn, "Full turn");
+                ui.radio_value(&mut check.motion, CheckMotion::Views, "Front, side and top");
+            });
+            let mut duration = check.duration.as_secs_f32();
+            ui.add(egui::Slider::new(&mut duration, 1.0..=10.0).text("Check seconds"));
+            check.duration = Duration::from_secs_f32(duration);
+            let mut fade = check.fade.as_secs_f32();
+            ui.add(egui::Slider::new(&mut fade, 0.0..=2.0).text("Fade in seconds"));
+            check.fade = Duration::from_secs_f32(fade);
+        });
+
+        let Some(current) = refs.current_reference else {
+            return;
+        };
+        ui.separator();
+        let reference = &mut refs.references[current];
+        let mut override_rotation = reference.rotation.is_some();
+        ui.checkbox(
+            &mut override_rotation,
+            format!("Override for {}", reference.name),
+        );
+        match (override_rotation, &mut reference.rotation) {
+            (true, Some(constraints)) => rotation_constraints_ui(ui, current, constraints),
+            (true, None) => reference.rotation = Some(rotation.constraints),
+            (false, _) => reference.rotation = None,
+        }
+
+        let mut override_creases = reference.crease_angles.is_some();
+        ui.checkbox(
+            &mut override_creases,
+            format!("Crease angles for {}", reference.name),
+        );
+        match (override_creases, &mut reference.crease_angles) {
+            (true, Some(angles)) => crease_angles_ui(ui, angles),
+            (true, None) => reference.crease_angles = Some(line_art.crease_angles),
+            (false, _) => reference.crease_angles = None,
+        }
+    });
+}
+
+fn crease_angles_ui(ui: &mut egui::Ui, angles: &mut CreaseAngles) {
+    ui.checkbox(&mut angles.adaptive, "Detect creases")
+        .on_hover_text("Draw the edges that aren't part of a smooth surface, using the normals.");
+    ui.add_enabled_ui(!angles.adaptive, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Crease angles");
+            ui.drag_angle(&mut angles.min);
+            ui.label("to");
+            ui.drag_angle(&mut angles.max);
+        })
+        .response
+        .on_hover_text("Edges whose faces meet at an angle in this range are drawn. 180° is flat.");
+    });
+    angles.min = angles.min.clamp(0.0, PI);
+    angles.max = angles.max.clamp(angles.min, PI);
+}
+
+const MIN_FOV: f32 = 5.0 * PI / 180.0;
+const MAX_FOV: f32 = 150.0 * PI / 180.0;
+
+fn rotation_constraints_ui(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash,
+    constraints: &mut RotationConstraints,
+) {
+    // This ui is shown for both the global and the per reference constraints, so the widgets
+    // need distinct ids.
+    ui.push_id(id, |ui| {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut constraints.mode, RotationMode::Free, "Free");
+            ui.radio_value(&mut constraints.mode, RotationMode::Upright, "Upright");
+            ui.radio_value(&mut constraints.mode, RotationMode::Tilted, "Tilted");
+        });
+        ui.add_enabled_ui(constraints.mode != RotationMode::Free, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Up axis");
+                ui.radio_value(&mut constraints.up, UpAxis::X, "X");
+                ui.radio_value(&mut constraints.up, UpAxis::Y, "Y");
+                ui.radio_value(&mut constraints.up, UpAxis::Z, "Z");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Max tilt");
+                ui.drag_angle(&mut constraints.max_tilt);
+                constraints.max_tilt = constraints.max_tilt.clamp(0.0, PI);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Camera");
+                ui.radio_value(&mut constraints.horizon, Horizon::Any, "Any");
+                ui.radio_value(&mut constraints.horizon, Horizon::Above, "Above");
+                ui.radio_value(&mut constraints.horizon, Horizon::EyeLevel, "Eye level");
+                ui.radio_value(&mut constraints.horizon, Horizon::Below, "Below");
+            });
+            ui.horizontal(|ui| {
+                let mut fixed = constraints.roll.is_some();
+                let mut roll = constraints.roll.unwrap_or(0.0);
+                ui.checkbox(&mut fixed, "Fixed roll");
+                ui.add_enabled_ui(fixed, |ui| ui.drag_angle(&mut roll));
+                constraints.roll = fixed.then_some(roll);
+            });
+            ui.add_enabled_ui(constraints.roll.is_none(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Max roll");
+                    ui.drag_angle(&mut constraints.max_roll);
+                    constraints.max_roll = constraints.max_roll.clamp(0.0, PI);
+                });
+            });
+        });
+    });
+}
+
+fn ui_session(mut contexts: EguiContexts, session: Res<Session>) {
+    egui::Window::new("Session")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "{} poses, {}",
+                session.poses.len(),
+                timer::format_minutes_seconds(session.total())
+            ));
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for pose in session.poses.iter().rev() {
+                        ui.horizontal(|ui| {
+                            ui.label(timer::format_minutes_seconds(pose.duration));
+                            ui.label(pose.reference.as_str());
+                        });
+                    }
+                });
+        });
+}
+
+fn next_pose_on_key(
+    mut contexts: EguiContexts,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_pose: EventWriter<NextPose>,
+) {
+    if keyboard_input.just_pressed(KeyCode::ArrowRight)
+        && !contexts.ctx_mut().wants_keyboard_input()
+    {
+        next_pose.send(NextPose);
+    }
+}
+
+/// Seconds displayed and entered as `mm:ss`.
+fn interval_drag_value(seconds: &mut f64) -> egui::DragValue<'_> {
+    egui::DragValue::new(seconds)
+        .speed(1.0)
+        .clamp_range(timer::MIN_INTERVAL.as_secs_f64()..=60.0 * 60.0)
+        .custom_formatter(|n, _| timer::format_minutes_seconds(Duration::from_secs_f64(n)))
+        .custom_parser(timer::parse_minutes_seconds)
+}
+
 #[derive(Component)]
 pub struct MainCamera;
 
@@ -153,13 +686,6 @@ fn setup(mut commands: Commands) {
             ..Default::default()
         },
         MainCamera,
+        OrbitCamera::default(),
     ));
 }
-
-fn zoom(mut input: EventReader<MouseWheel>, mut camera: Query<&mut Transform, With<MainCamera>>) {
-    for event in input.read() {
-        for mut transform in camera.iter_mut() {
-            transform.translation.z -= event.y * 0.5;
-        }
-    }
-}
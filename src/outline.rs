@@ -2,10 +2,17 @@
 
 use bevy::{
     math::DVec3,
+    pbr::{MaterialPipeline, MaterialPipelineKey},
     prelude::*,
     render::{
-        mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues},
-        render_resource::VertexFormat,
+        mesh::{
+            Indices, MeshVertexAttribute, MeshVertexBufferLayout, PrimitiveTopology,
+            VertexAttributeValues,
+        },
+        render_resource::{
+            AsBindGroup, Face, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+            VertexFormat,
+        },
     },
     utils::{FloatOrd, HashMap},
 };
@@ -14,12 +21,61 @@ use bevy::{
 pub const ATTRIBUTE_OUTLINE_NORMAL: MeshVertexAttribute =
     MeshVertexAttribute::new("Outline_Normal", 1585570526, VertexFormat::Float32x3);
 
-pub fn generate_outline_mesh(mesh: &Mesh, thickness: f32) -> Result<Mesh, GenerateOutlineError> {
-    let mut outline_mesh = mesh.clone();
+const OUTLINE_SHADER: &str = "custom_material.wgsl";
 
-    smooth_normals(&mut outline_mesh)?;
-    move_vertices_along_normals(&mut outline_mesh, thickness)?;
-    Ok(outline_mesh)
+/// Draws inverted hull outlines with [`OutlineMaterial`].
+pub struct OutlinePlugin;
+
+impl Plugin for OutlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<OutlineMaterial> {
+            // The outline has no place in the depth and normal prepasses.
+            prepass_enabled: false,
+            ..default()
+        })
+        // The outlines are spawned into the reference scenes, which clone their components by
+        // reflection.
+        .register_asset_reflect::<OutlineMaterial>();
+    }
+}
+
+/// Extrudes the vertices of a mesh along [`ATTRIBUTE_OUTLINE_NORMAL`] in the vertex shader and
+/// draws the back faces, so the mesh itself can be drawn as its own outline.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct OutlineMaterial {
+    #[uniform(0)]
+    pub color: Color,
+    /// In world units, or in physical pixels with `constant_width`. The outline is equally thick
+    /// along every axis of a scaled mesh.
+    #[uniform(0)]
+    pub thickness: f32,
+    /// 1 to keep the outline equally wide on screen at any distance.
+    #[uniform(0)]
+    pub constant_width: u32,
+}
+
+impl Material for OutlineMaterial {
+    fn vertex_shader() -> ShaderRef {
+        OUTLINE_SHADER.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        OUTLINE_SHADER.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.vertex.buffers = vec![layout.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            ATTRIBUTE_OUTLINE_NORMAL.at_shader_location(1),
+        ])?];
+        descriptor.primitive.cull_mode = Some(Face::Front);
+        Ok(())
+    }
 }
 
 pub fn smooth_normals(mesh: &mut Mesh) -> Result<(), GenerateOutlineError> {
@@ -38,11 +94,6 @@ pub fn smooth_normals(mesh: &mut Mesh) -> Result<(), GenerateOutlineError> {
             v.into(),
         )),
     }?;
-    let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
-        Some(VertexAttributeValues::Float32x3(p)) => Some(p),
-        _ => None,
-    };
-
     let mut map = HashMap::<[FloatOrd; 3], DVec3>::with_capacity(positions.len());
 
     // iteration the complicated way... don't know  a better way to do this without heap allocating
@@ -71,30 +122,15 @@ pub fn smooth_normals(mesh: &mut Mesh) -> Result<(), GenerateOutlineError> {
             let v1 = DVec3::from(p1 - p0) * SCALE;
             let v2 = DVec3::from(p2 - p0) * SCALE;
             let angle = (v1).angle_between(v2);
-            let n = map
-                .entry([
-                    FloatOrd(p0.x as f32),
-                    FloatOrd(p0.y as f32),
-                    FloatOrd(p0.z as f32),
-                ])
-                .or_default();
+            let n = map.entry(weld(p0.to_array())).or_default();
             *n += angle * v1.cross(v2).normalize_or_zero();
-
-            // if let Some(ns) = normals {
-            //     // Use vertex normal
-            //     DVec3::from(Vec3::from(ns[j0]))
-            // } else {
-            //     // Calculate face normal
-            //     (p1 - p0).cross(p2 - p0).normalize_or_zero()
-            // };
         }
     }
 
     let mut outlines = Vec::with_capacity(positions.len());
     for p in positions.iter() {
-        let key = [FloatOrd(p[0]), FloatOrd(p[1]), FloatOrd(p[2])];
         let v = map
-            .get(&key)
+            .get(&weld(*p))
             .copied()
             .unwrap_or(DVec3::ZERO)
             .normalize_or_zero();
@@ -108,45 +144,11 @@ pub fn smooth_normals(mesh: &mut Mesh) -> Result<(), GenerateOutlineError> {
     Ok(())
 }
 
-/// Moves the vertices of the mesh along their normals by distance.
-pub fn move_vertices_along_normals(
-    mesh: &mut Mesh,
-    distance: f32,
-) -> Result<(), GenerateOutlineError> {
-    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION).ok_or(
-        GenerateOutlineError::MissingVertexAttribute(Mesh::ATTRIBUTE_POSITION.name),
-    )? {
-        VertexAttributeValues::Float32x3(p) => Ok(p),
-        v => Err(GenerateOutlineError::InvalidVertexAttributeFormat(
-            Mesh::ATTRIBUTE_POSITION.name,
-            VertexFormat::Float32x3,
-            v.into(),
-        )),
-    }?;
-    let normals = match mesh.attribute(ATTRIBUTE_OUTLINE_NORMAL).ok_or(
-        GenerateOutlineError::MissingVertexAttribute(ATTRIBUTE_OUTLINE_NORMAL.name),
-    )? {
-        VertexAttributeValues::Float32x3(p) => Ok(p),
-        v => Err(GenerateOutlineError::InvalidVertexAttributeFormat(
-            ATTRIBUTE_OUTLINE_NORMAL.name,
-            VertexFormat::Float32x3,
-            v.into(),
-        )),
-    }?;
-
-    let mut new_positions = Vec::with_capacity(positions.len());
-    for (p, n) in positions.iter().zip(normals.iter()) {
-        new_positions.push([
-            p[0] + n[0] * distance,
-            p[1] + n[1] * distance,
-            p[2] + n[2] * distance,
-        ]);
-    }
-    mesh.insert_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        VertexAttributeValues::Float32x3(new_positions),
-    );
-    Ok(())
+/// Snaps a position to a grid, so duplicated vertices along uv seams share their normal even if
+/// their positions differ slightly.
+fn weld(position: [f32; 3]) -> [FloatOrd; 3] {
+    const WELD_DISTANCE: f32 = 1e-5;
+    position.map(|x| FloatOrd((x / WELD_DISTANCE).round() * WELD_DISTANCE))
 }
 
 /// Failed to generate outline normals for the mesh.
@@ -159,3 +161,36 @@ pub enum GenerateOutlineError {
     #[error("the '{0}' vertex attribute should have {1:?} format, but had {2:?} format")]
     InvalidVertexAttributeFormat(&'static str, VertexFormat, VertexFormat),
 }
+
+#[test]
+fn test_smooth_normals() {
+    // The UV sphere duplicates the vertices along its seam, at slightly different positions.
+    let mut mesh = Sphere::new(1.0).mesh().uv(64, 32);
+    smooth_normals(&mut mesh).unwrap();
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        panic!("missing positions");
+    };
+    let Some(VertexAttributeValues::Float32x3(outline_normals)) =
+        mesh.attribute(ATTRIBUTE_OUTLINE_NORMAL)
+    else {
+        panic!("missing outline normals");
+    };
+
+    let mut seam = 0;
+    for (i, (&position, &outline_normal)) in positions.iter().zip(outline_normals).enumerate() {
+        let position = Vec3::from(position);
+        if positions[..i]
+            .iter()
+            .any(|&p| Vec3::from(p).distance(position) < 1e-6)
+        {
+            seam += 1;
+        }
+        assert!(
+            Vec3::from(outline_normal).distance(position.normalize()) < 1e-2,
+            "{position} {outline_normal:?}"
+        );
+    }
+    assert!(seam > 0);
+}
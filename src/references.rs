@@ -1,93 +1,162 @@
-use std::time::{Duration, Instant};
+use std::f32::consts::PI;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
 
+use bevy::asset::io::file::FileAssetReader;
+use bevy::pbr::NotShadowCaster;
 use bevy::render::mesh::PrimitiveTopology;
-use bevy::render::render_resource::Face;
-use bevy::render::view::RenderLayers;
 use bevy::utils::{FloatOrd, HashMap, HashSet};
 use bevy::{asset::LoadedFolder, gltf::Gltf, prelude::*};
 use bevy_mod_picking::prelude::*;
 use rand::Rng;
 
-use crate::outline::generate_outline_mesh;
+use crate::arrangement::{ArrangedObject, ArrangementSettings};
+use crate::line_art::{CreaseAngles, DEFAULT_THICKNESS};
+use crate::outline::{smooth_normals, OutlineMaterial};
 use crate::picking_ext::PointerEvent;
+use crate::rotation::{RotationConstraints, RotationSettings};
+use crate::session::{Pose, Session};
+use crate::timer::TimerSet;
 use crate::wrapping_cursor::{Wrap, WrappingCursorState};
 use crate::MainCamera;
 
-const LINE_ART_THICKNESS: f32 = 0.02;
-const TIMER_INTERVAL: f32 = 3.0;
+/// Radians a reference rotates per pixel it is dragged.
+const DRAG_ROTATION_SPEED: f32 = 0.01;
 /// Could consider not hardcoding this path.
 const REFERNCE_FOLDER: &str = "references";
+/// The crease angles of the references that have their own, next to the reference folder.
+/// A file of its own, as the glTF files of the references are only read.
+const CREASE_ANGLES_FILE: &str = "crease_angles.txt";
+/// How long the crease angles have to stay the same before they are saved, so dragging them
+/// doesn't write the file every frame.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
 
 pub struct ReferencePlugin;
 
 impl Plugin for ReferencePlugin {
     fn build(&self, app: &mut App) {
         app.init_gizmo_group::<LineArtGizmo>()
-            .add_event::<TimerEvent>()
-            .add_systems(
-                Startup,
-                (insert_reference_manager, setup_timer, setup_gizmo_config),
-            )
+            .add_event::<NewPose>()
+            .add_event::<EndPose>()
+            .init_resource::<Session>()
+            .init_resource::<RotationSettings>()
+            .init_resource::<ScaleSettings>()
+            .init_resource::<ArrangementSettings>()
+            .init_resource::<Normalization>()
+            .add_systems(Startup, insert_reference_manager)
             .add_systems(
                 Update,
                 (
                     listen_for_loaded_folder,
-                    (update_timer, update_reference).chain(),
+                    save_crease_angles,
+                    update_reference.after(TimerSet).in_set(ReferenceSet),
                 ),
             );
     }
 }
 
-fn insert_reference_manager(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(References::new(&asset_server));
+/// Systems reacting to [`NewPose`] should run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReferenceSet;
+
+/// Sent when the current pose is over and the next references should be shown.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EndPose {
+    /// How long the pose lasted.
+    pub elapsed: Duration,
 }
 
-fn setup_gizmo_config(mut config_store: ResMut<GizmoConfigStore>) {
-    let (mut config, _) = config_store.config_mut::<LineArtGizmo>();
-    config.line_width = LINE_ART_THICKNESS * 900.;
-    config.line_perspective = true;
-    // config.depth_bias = -10.;
+/// Sent when references are shown in a new pose.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct NewPose {
+    /// Bounding sphere around all shown references.
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+fn insert_reference_manager(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(References::new(&asset_server));
 }
 
 fn update_reference(
-    mut gizmo: Gizmos<LineArtGizmo>,
     mut commands: Commands,
     mut refs: ResMut<References>,
-    mut timer_events: EventReader<TimerEvent>,
-    transform_query: Query<&Transform>,
+    mut session: ResMut<Session>,
+    mut end_poses: EventReader<EndPose>,
+    mut new_poses: EventWriter<NewPose>,
+    rotation_settings: Res<RotationSettings>,
+    scale_settings: Res<ScaleSettings>,
+    arrangement: Res<ArrangementSettings>,
+    camera_query: Query<&GlobalTransform, With<MainCamera>>,
 ) {
     if refs.references.is_empty() {
         return;
     }
 
-    if let Some(current) = refs.current_reference {
-        let Reference { entity, edges, .. } = &refs.references[current];
-
-        let transform = *transform_query.get(*entity).unwrap();
-
-        for edge in edges.iter() {
-            gizmo.line(transform * edge.0, transform * edge.1, Color::WHITE);
-        }
-    }
-
     // if there is no current reference set yet we do run this function despite the timer not having expired.
-    if timer_events.read().count() == 0 && refs.current_reference.is_some() {
+    let end_pose = end_poses.read().last().copied();
+    if end_pose.is_none() && refs.current_reference.is_some() {
         return;
     }
 
-    if let Some(current) = refs.current_reference {
+    for &shown in refs.shown.iter() {
         commands
-            .entity(refs.references[current].entity)
+            .entity(refs.references[shown].entity)
             .insert(Visibility::Hidden);
-    };
+    }
+    if let (Some(end_pose), false) = (end_pose, refs.shown.is_empty()) {
+        let names: Vec<&str> = refs
+            .shown
+            .iter()
+            .map(|&i| refs.references[i].name.as_str())
+            .collect();
+        session.poses.push(Pose {
+            reference: Name::new(names.join(", ")),
+            duration: end_pose.elapsed,
+        });
+    }
+
+    let next = refs.next_references(arrangement.count);
+    refs.current_reference = next.first().copied();
+    if next.is_empty() {
+        refs.shown.clear();
+        return;
+    }
 
-    if let Some(next) = refs.next_reference() {
-        refs.current_reference = Some(next);
-        commands.entity(refs.references[next].entity).insert((
-            Visibility::Visible,
-            Transform::from_rotation(random_rotation()),
-        ));
+    let view_dir = camera_query
+        .get_single()
+        .map_or(Vec3::Z, |camera| camera.translation().normalize_or_zero());
+    let mut rng = rand::thread_rng();
+    let mut transforms = Vec::with_capacity(next.len());
+    let mut objects = Vec::with_capacity(next.len());
+    for &i in next.iter() {
+        let reference = &refs.references[i];
+        let rotation = rotation_settings.sample(&mut rng, view_dir, reference.rotation.as_ref());
+        let scale = scale_settings.sample(&mut rng);
+        let transform = Transform::from_rotation(rotation).with_scale(scale);
+        objects.push(ArrangedObject {
+            radius: reference.radius * scale.max_element(),
+            bottom: -reference.lowest_point(&transform),
+        });
+        transforms.push(transform);
     }
+    let positions = arrangement.arrange(&objects, &mut rng);
+
+    let center = positions.iter().sum::<Vec3>() / positions.len() as f32;
+    let radius = positions
+        .iter()
+        .zip(objects.iter())
+        .map(|(position, object)| position.distance(center) + object.radius)
+        .fold(0.0, f32::max);
+
+    for ((&i, transform), position) in next.iter().zip(transforms).zip(positions) {
+        commands
+            .entity(refs.references[i].entity)
+            .insert((Visibility::Visible, transform.with_translation(position)));
+    }
+    refs.shown = next;
+    new_poses.send(NewPose { center, radius });
 }
 
 #[derive(Resource)]
@@ -95,14 +164,91 @@ pub struct References {
     pub references: Vec<Reference>,
     pub disabled_references: HashSet<usize>,
     pub current_reference: Option<usize>,
+    /// All references that are currently visible, starting with the current one.
+    pub shown: Vec<usize>,
     pub loading_folder: Handle<LoadedFolder>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Reference {
     pub name: Name,
+    /// The asset path of the scene, e.g. `references/shapes.glb#Scene0`, unlike the name unique.
+    pub path: String,
     pub entity: Entity,
-    pub edges: Vec<(Vec3, Vec3)>,
+    /// Every edge of the meshes and how its faces meet, see [`edge_angles`].
+    pub edges: Vec<(Vec3, Vec3, EdgeKind)>,
+    /// Overrides the global [`crate::line_art::LineArtSettings::crease_angles`] for this reference.
+    /// Saved by [`Reference::path`] in [`CREASE_ANGLES_FILE`] next to the references, not in the
+    /// reference's glTF file.
+    pub crease_angles: Option<CreaseAngles>,
+    /// Every triangle of the meshes.
+    pub triangles: Vec<[Vec3; 3]>,
+    /// The curvature of the meshes for the feature lines.
+    pub surface: Surface,
+    /// Overrides the global [`RotationSettings::constraints`] for this reference.
+    pub rotation: Option<RotationConstraints>,
+    /// Shared by the outlines of all meshes of the reference.
+    pub outline: Handle<OutlineMaterial>,
+    pub materials: Vec<ReferenceMaterial>,
+    /// Radius of the bounding sphere around the reference's origin.
+    pub radius: f32,
+}
+
+/// A material of a reference with the properties the line art style changes.
+#[derive(Debug, Clone)]
+pub struct ReferenceMaterial {
+    pub handle: Handle<StandardMaterial>,
+    pub alpha: f32,
+    pub alpha_mode: AlphaMode,
+}
+
+impl Reference {
+    /// The sharp edges drawn as line art, using the reference's own crease angles if it has them.
+    /// Edges shared by more than two faces are always drawn.
+    pub fn creases<'a>(
+        &'a self,
+        crease_angles: &CreaseAngles,
+    ) -> impl Iterator<Item = (Vec3, Vec3)> + 'a {
+        let crease_angles = self.crease_angles.unwrap_or(*crease_angles);
+        self.edges
+            .iter()
+            .filter(move |(_, _, kind)| match kind {
+                EdgeKind::Manifold { angle, smooth, .. } => crease_angles.contains(*angle, *smooth),
+                EdgeKind::NonManifold => true,
+                EdgeKind::Boundary => false,
+            })
+            .map(|&(a, b, _)| (a, b))
+    }
+
+    /// Every edge except the ones between coplanar faces, which only show how faces were split
+    /// into triangles.
+    pub fn wireframe(&self) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+        self.edges
+            .iter()
+            .filter(|(_, _, kind)| match kind {
+                EdgeKind::Manifold { angle, .. } => PI - angle > COPLANAR_BEND,
+                _ => true,
+            })
+            .map(|&(a, b, _)| (a, b))
+    }
+
+    /// The edges of open surfaces that are part of only one face.
+    pub fn boundaries(&self) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+        self.edges
+            .iter()
+            .filter(|(_, _, kind)| *kind == EdgeKind::Boundary)
+            .map(|&(a, b, _)| (a, b))
+    }
+
+    /// The height of the lowest vertex of the reference with the given transform.
+    pub fn lowest_point(&self, transform: &Transform) -> f32 {
+        self.triangles
+            .iter()
+            .flatten()
+            .map(|p| transform.transform_point(*p).y)
+            .reduce(f32::min)
+            .unwrap_or(0.0)
+    }
 }
 
 /// Marker
@@ -115,31 +261,30 @@ impl References {
             references: Vec::new(),
             disabled_references: default(),
             current_reference: None,
+            shown: Vec::new(),
             loading_folder: asset_server.load_folder(REFERNCE_FOLDER),
         }
     }
 
-    pub fn next_reference(&self) -> Option<usize> {
-        let start = match self.current_reference {
-            Some(current) => current + 1,
-            None => {
-                if self.disabled_references.len() == self.references.len() {
-                    return None;
-                } else {
-                    0
-                }
-            }
+    /// Up to `count` distinct active references following the ones currently shown, or the one
+    /// picked with [`References::set_current`].
+    pub fn next_references(&self, count: usize) -> Vec<usize> {
+        let active = self.references.len() - self.disabled_references.len();
+        let last = match (self.current_reference, self.shown.first()) {
+            // The current reference was picked by the user, so continue from there.
+            (Some(current), first) if first != Some(&current) => Some(current),
+            _ => self.shown.last().copied(),
+        };
+        let start = match last {
+            Some(last) => last + 1,
+            None => 0,
         };
 
-        // We are guaranteed to find a reference because the above match statement ensures it.
-        for i in start.. {
-            let i = i % self.references.len();
-            if !self.disabled_references.contains(&i) {
-                return Some(i);
-            }
-        }
-
-        unreachable!()
+        (start..start + self.references.len())
+            .map(|i| i % self.references.len())
+            .filter(|i| !self.disabled_references.contains(i))
+            .take(count.min(active))
+            .collect()
     }
 
     pub fn set_current(&mut self, index: usize) {
@@ -163,80 +308,134 @@ impl References {
         scenes: &mut Assets<Scene>,
         meshes: &mut Assets<Mesh>,
         materials: &mut Assets<StandardMaterial>,
+        outline_materials: &mut Assets<OutlineMaterial>,
+        normalization: &Normalization,
         camera_transform: &Transform,
     ) {
         let folder = folders.get(&self.loading_folder).unwrap();
+        let saved_crease_angles = fs::read_to_string(crease_angles_path())
+            .map(|text| parse_crease_angles(&text))
+            .unwrap_or_default();
         for reference in folder.handles.iter() {
             match reference.clone().try_typed::<Gltf>() {
                 Ok(handle) => {
                     for scene_handle in gltfs.get(&handle).unwrap().scenes.clone() {
+                        let path = scene_handle
+                            .path()
+                            .map_or_else(String::new, ToString::to_string);
                         let scene = scenes.get_mut(&scene_handle).unwrap();
                         let world = &mut scene.world;
 
-                        let mut q = world
-                            .query::<(&Name, &Handle<Mesh>, &Handle<StandardMaterial>, &Parent)>();
+                        let mut q = world.query::<(
+                            Entity,
+                            &Name,
+                            &Handle<Mesh>,
+                            &Handle<StandardMaterial>,
+                            &Parent,
+                        )>();
 
                         let mut edges = Vec::new();
-                        let mut outline_meshes = Vec::new();
+                        let mut reference_triangles = Vec::new();
+                        let mut reference_materials = Vec::new();
+                        let mut mesh_parents = Vec::new();
                         // awkward workaround to get the name of the object
                         // (assuming a bunch of things like that there is only one object and only one mesh).
                         let mut name = None;
-                        for (n, mesh_handle, material, parent) in q.iter(world) {
+                        for (entity, n, mesh_handle, material, parent) in q.iter(world) {
                             name = Some(n.clone());
                             let mesh = meshes.get(mesh_handle).unwrap();
                             if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
                                 warn!("Mesh is not a triangle list: {:?}", mesh_handle);
                                 continue;
                             }
-                            edges.extend(sharp_edge_lines(
-                                mesh,
-                                (45.0f32.to_radians(), 135.0f32.to_radians()),
+                            mesh_parents.push((
+                                mesh_handle.clone(),
+                                parent.get(),
+                                scene_transform(world, entity),
                             ));
 
-                            let outline_mesh =
-                                generate_outline_mesh(mesh, LINE_ART_THICKNESS).unwrap();
-                            let outline_mesh_handle = meshes.add(outline_mesh);
-
-                            outline_meshes.push((parent.get(), outline_mesh_handle));
-
-                            let material = materials.get_mut(material).unwrap();
-                            // For transparent material
-                            // material.base_color = material.base_color.with_a(0.2);
-                            // material.alpha_mode = AlphaMode::Blend;
-                            // material.cull_mode = Some(Face::Back);
+                            let original = materials.get(material).unwrap();
+                            reference_materials.push(ReferenceMaterial {
+                                handle: material.clone(),
+                                alpha: original.base_color.a(),
+                                alpha_mode: original.alpha_mode,
+                            });
                         }
 
-                        let material = materials.add(StandardMaterial {
-                            base_color: Color::WHITE,
-                            unlit: true,
-                            cull_mode: Some(Face::Front),
-                            ..Default::default()
+                        // Copies of the meshes in the space of the reference, which the line art
+                        // is computed in. The meshes themselves may be shared with other scenes.
+                        let mut reference_meshes: Vec<Mesh> = mesh_parents
+                            .iter()
+                            .map(|(mesh, _, transform)| {
+                                meshes.get(mesh).unwrap().clone().transformed_by(*transform)
+                            })
+                            .collect();
+                        let normalization = normalization.transform(&reference_meshes);
+                        for mesh in reference_meshes.iter_mut() {
+                            mesh.transform_by(normalization);
+                            edges.extend(edge_angles(mesh));
+                            reference_triangles.extend(triangles(mesh));
+                        }
+                        let radius = reference_triangles
+                            .iter()
+                            .flatten()
+                            .map(|p| p.length())
+                            .fold(0.0, f32::max);
+                        let surface = Surface::new(reference_meshes.iter());
+
+                        let outline = outline_materials.add(OutlineMaterial {
+                            color: Color::WHITE,
+                            thickness: DEFAULT_THICKNESS,
+                            constant_width: 0,
                         });
+                        for (mesh_handle, parent, _) in mesh_parents {
+                            let mesh = meshes.get_mut(&mesh_handle).unwrap();
+                            // The outline material extrudes the mesh itself along these.
+                            smooth_normals(mesh).unwrap();
 
-                        for (parent, outline_mesh_handle) in outline_meshes {
                             world.entity_mut(parent).with_children(|parent| {
-                                parent.spawn(PbrBundle {
-                                    mesh: outline_mesh_handle,
-                                    material: material.clone(),
-                                    ..default()
-                                });
+                                // Every material is drawn into the shadow maps, there is no
+                                // `MaterialPlugin` setting for that in this Bevy version.
+                                parent.spawn((
+                                    MaterialMeshBundle {
+                                        mesh: mesh_handle,
+                                        material: outline.clone(),
+                                        ..default()
+                                    },
+                                    NotShadowCaster,
+                                ));
                             });
                         }
 
                         let reference_entity = commands
                             .spawn((
-                                SceneBundle {
-                                    scene: scene_handle,
+                                SpatialBundle {
                                     visibility: Visibility::Hidden,
                                     ..default()
                                 },
                                 ReferenceMarker,
+                                On::<PointerEvent>::run(rotate_reference),
                             ))
+                            .with_children(|parent| {
+                                parent.spawn(SceneBundle {
+                                    scene: scene_handle,
+                                    transform: normalization,
+                                    ..default()
+                                });
+                            })
                             .id();
                         self.references.push(Reference {
                             name: name.unwrap_or_default(),
+                            crease_angles: saved_crease_angles.get(&path).copied(),
+                            path,
                             entity: reference_entity,
                             edges,
+                            triangles: reference_triangles,
+                            surface,
+                            rotation: None,
+                            outline,
+                            materials: reference_materials,
+                            radius,
                         });
                     }
                 }
@@ -248,6 +447,46 @@ impl References {
     }
 }
 
+/// Rotates a reference like a trackball while it is dragged with the primary button.
+/// Alt + drag is left to the camera.
+fn rotate_reference(
+    mut transforms: Query<&mut Transform>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut wrapping_cursor: ResMut<NextState<WrappingCursorState>>,
+    mut wrap_events: EventReader<Wrap>,
+    event: Listener<PointerEvent>,
+) {
+    let alt = keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    match &**event {
+        PointerEvent::DragStart(e) if e.button == PointerButton::Primary && !alt => {
+            wrapping_cursor.set(WrappingCursorState::On);
+        }
+        PointerEvent::Drag(e) if e.button == PointerButton::Primary && !alt => {
+            // The cursor jumping to the other side of the window isn't a movement.
+            if wrap_events.read().len() != 0 {
+                return;
+            }
+            let (Ok(mut transform), Ok(camera)) =
+                (transforms.get_mut(event.listener()), camera.get_single())
+            else {
+                return;
+            };
+            // Screen y points down, so dragging right or down turns the front of the reference
+            // that way.
+            let axis = camera.compute_transform().rotation * Vec3::new(e.delta.y, e.delta.x, 0.0);
+            if let Some(axis) = axis.try_normalize() {
+                let rotation = Quat::from_axis_angle(axis, e.delta.length() * DRAG_ROTATION_SPEED);
+                transform.rotation = (rotation * transform.rotation).normalize();
+            }
+        }
+        PointerEvent::DragEnd(e) if e.button == PointerButton::Primary => {
+            wrapping_cursor.set(WrappingCursorState::Off);
+        }
+        _ => {}
+    }
+}
+
 fn listen_for_loaded_folder(
     mut commands: Commands,
     mut reference_manager: ResMut<References>,
@@ -257,6 +496,8 @@ fn listen_for_loaded_folder(
     mut scenes: ResMut<Assets<Scene>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut outline_materials: ResMut<Assets<OutlineMaterial>>,
+    normalization: Res<Normalization>,
     camera_query: Query<&Transform, With<MainCamera>>,
 ) {
     for e in events.read() {
@@ -269,6 +510,8 @@ fn listen_for_loaded_folder(
                     &mut scenes,
                     &mut meshes,
                     &mut materials,
+                    &mut outline_materials,
+                    &normalization,
                     camera_query.single(),
                 );
             }
@@ -276,28 +519,238 @@ fn listen_for_loaded_folder(
     }
 }
 
-#[derive(Default, Reflect, GizmoConfigGroup)]
-pub struct LineArtGizmo;
+/// How references are moved and scaled when they are loaded, so that all of them have a similar
+/// size regardless of how they were modelled.
+#[derive(Resource, Debug, Clone)]
+pub struct Normalization {
+    pub centering: Centering,
+    /// Radius of the bounding sphere around the new origin every reference is scaled to.
+    pub radius: f32,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            centering: Centering::Bounds,
+            radius: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Centering {
+    /// The center of the axis aligned bounding box.
+    Bounds,
+    /// The center of mass of the surface.
+    Centroid,
+}
+
+impl Normalization {
+    /// The transform that moves and scales all meshes of one reference together, given in the
+    /// space of its scene. It is set on the root of the scene, the meshes are left as they are.
+    fn transform(&self, meshes: &[Mesh]) -> Transform {
+        let positions = || {
+            meshes
+                .iter()
+                .filter_map(|mesh| mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3())
+                .flatten()
+                .map(|p| Vec3::from(*p))
+        };
+
+        let center = match self.centering {
+            Centering::Bounds => {
+                let (min, max) = positions().fold(
+                    (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+                    |(min, max), p| (min.min(p), max.max(p)),
+                );
+                if min.x > max.x {
+                    return Transform::IDENTITY;
+                }
+                (min + max) / 2.0
+            }
+            Centering::Centroid => {
+                let (weighted, area) = meshes.iter().flat_map(triangles).fold(
+                    (Vec3::ZERO, 0.0),
+                    |(weighted, area), [a, b, c]| {
+                        let triangle_area = (b - a).cross(c - a).length() / 2.0;
+                        (
+                            weighted + (a + b + c) / 3.0 * triangle_area,
+                            area + triangle_area,
+                        )
+                    },
+                );
+                if area > 0.0 {
+                    weighted / area
+                } else {
+                    Vec3::ZERO
+                }
+            }
+        };
+        let radius = positions().map(|p| p.distance(center)).fold(0.0, f32::max);
+        if radius == 0.0 {
+            return Transform::IDENTITY;
+        }
+        let factor = self.radius / radius;
+        Transform::from_translation(-center * factor).with_scale(Vec3::splat(factor))
+    }
+}
+
+/// The transform of an entity of a scene relative to the root of the scene.
+fn scene_transform(world: &World, entity: Entity) -> Transform {
+    let mut transform = GlobalTransform::IDENTITY;
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        let entity = world.entity(entity);
+        if let Some(local) = entity.get::<Transform>() {
+            transform = GlobalTransform::from(*local) * transform;
+        }
+        current = entity.get::<Parent>().map(Parent::get);
+    }
+    transform.compute_transform()
+}
+
+fn crease_angles_path() -> PathBuf {
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join(CREASE_ANGLES_FILE)
+}
+
+/// Reads lines like `0.5235988 2.6179939 fixed references/shapes.glb#Scene0`, with the angles in
+/// radians and whether they are [`CreaseAngles::adaptive`], into the crease angles of each
+/// reference by [`Reference::path`].
+fn parse_crease_angles(text: &str) -> HashMap<String, CreaseAngles> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().splitn(4, ' ');
+            let min = fields.next()?.parse().ok()?;
+            let max = fields.next()?.parse().ok()?;
+            let adaptive = match fields.next()? {
+                "adaptive" => true,
+                "fixed" => false,
+                _ => return None,
+            };
+            let path = fields.next()?.to_string();
+            Some((path, CreaseAngles { adaptive, min, max }))
+        })
+        .collect()
+}
 
-fn sharp_edge_lines(mesh: &Mesh, radian_range: (f32, f32)) -> Vec<(Vec3, Vec3)> {
-    let edge_angles = edge_angles(mesh);
-    // println!("{:?}", edge_angles);
+fn format_crease_angles(angles: &HashMap<String, CreaseAngles>) -> String {
+    let mut paths: Vec<_> = angles.keys().collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let angles = angles[path];
+            let adaptive = if angles.adaptive { "adaptive" } else { "fixed" };
+            // Printed exactly, so they are read back the same.
+            format!("{} {} {adaptive} {path}\n", angles.min, angles.max)
+        })
+        .collect()
+}
+
+/// The crease angles of the references last seen by [`save_crease_angles`].
+#[derive(Default)]
+struct CreaseAnglesChanges {
+    seen: Vec<Option<CreaseAngles>>,
+    /// When they last changed, if they haven't been saved since.
+    changed: Option<Duration>,
+}
 
-    let mut lines = Vec::new();
-    for (a, b, angle) in edge_angles {
-        let angle = angle.unwrap_or(0.0);
-        if radian_range.0 < angle && angle < radian_range.1 {
-            lines.push((a, b));
+/// Writes the crease angles of the references to [`CREASE_ANGLES_FILE`] once they stopped
+/// changing for [`SAVE_DELAY`], keeping the ones of references that aren't loaded.
+fn save_crease_angles(
+    time: Res<Time<Real>>,
+    refs: Res<References>,
+    mut changes: Local<CreaseAnglesChanges>,
+) {
+    // The references are marked as changed by the ui every frame, so compare them instead.
+    let current: Vec<_> = refs.references.iter().map(|r| r.crease_angles).collect();
+    if changes.seen != current {
+        // Unless the references were just loaded with the saved crease angles.
+        if changes.seen.len() == current.len() {
+            changes.changed = Some(time.elapsed());
         }
+        changes.seen = current;
+        return;
+    }
+    match changes.changed {
+        Some(changed) if time.elapsed() - changed >= SAVE_DELAY => changes.changed = None,
+        _ => return,
     }
 
-    lines
+    let path = crease_angles_path();
+    let mut angles = fs::read_to_string(&path)
+        .map(|text| parse_crease_angles(&text))
+        .unwrap_or_default();
+    // Without a path there is nothing to tell the reference apart by.
+    for reference in refs.references.iter().filter(|r| !r.path.is_empty()) {
+        match reference.crease_angles {
+            Some(crease_angles) => angles.insert(reference.path.clone(), crease_angles),
+            None => angles.remove(&reference.path),
+        };
+    }
+    if let Err(error) = fs::write(&path, format_crease_angles(&angles)) {
+        warn!("Failed to save the crease angles to {path:?}: {error}");
+    }
+}
+
+/// The corners of every triangle of a triangle list mesh.
+fn triangles(mesh: &Mesh) -> Vec<[Vec3; 3]> {
+    let Some(positions) = mesh
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .and_then(|p| p.as_float3())
+    else {
+        return Vec::new();
+    };
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+    indices
+        .chunks_exact(3)
+        .map(|t| [t[0], t[1], t[2]].map(|i| Vec3::from(positions[i])))
+        .collect()
+}
+
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct LineArtGizmo;
+
+/// Vertices closer than this are merged when finding edges. References are normalized to a
+/// radius of about 1.0.
+const WELD_DISTANCE: f32 = 1e-5;
+/// Vertex normals closer than this are considered the same, i.e. the faces are shaded smoothly.
+const SMOOTH_NORMAL_ANGLE: f32 = 5.0 * PI / 180.0;
+/// Edges bending less than this are between coplanar faces, e.g. the diagonal of a flat quad.
+const COPLANAR_BEND: f32 = 0.5 * PI / 180.0;
+/// Without informative normals, edges bending more than this are always creases.
+const MAX_SMOOTH_BEND: f32 = 50.0 * PI / 180.0;
+/// Without informative normals, an edge bending more than this many times as much as every
+/// neighboring edge is a crease.
+const CREASE_BEND_RATIO: f32 = 2.0;
+
+/// How the faces of an edge meet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeKind {
+    /// Part of a single face, the rim of an open surface.
+    Boundary,
+    /// Shared by two faces meeting at `angle` in radians.
+    Manifold {
+        angle: f32,
+        /// The edge is part of the tessellation of a smooth surface rather than a crease.
+        /// Decided by the authored normals, or by the curvature around the edge for flat shaded
+        /// meshes.
+        smooth: bool,
+        /// The normals of the two faces.
+        normals: [Vec3; 2],
+    },
+    /// Shared by more than two faces.
+    NonManifold,
 }
 
 /// Panics if the mesh is not a triangle list.
-/// Returns a list of all edges and the angle between the connected faces (in radians).
-/// If the edge is only connected to one face the angle is None.
-fn edge_angles(mesh: &Mesh) -> Vec<(Vec3, Vec3, Option<f32>)> {
+/// Returns a list of all edges and how the connected faces meet.
+fn edge_angles(mesh: &Mesh) -> Vec<(Vec3, Vec3, EdgeKind)> {
     assert!(mesh.primitive_topology() == PrimitiveTopology::TriangleList);
 
     let vertices = mesh
@@ -305,67 +758,397 @@ fn edge_angles(mesh: &Mesh) -> Vec<(Vec3, Vec3, Option<f32>)> {
         .unwrap()
         .as_float3()
         .unwrap();
-    let mut indices_iter = mesh.indices().unwrap().iter();
-    // println!("{}", mesh.indices().unwrap().len());
+    let normals = mesh
+        .attribute(Mesh::ATTRIBUTE_NORMAL)
+        .and_then(|normals| normals.as_float3());
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..vertices.len()).collect(),
+    };
 
-    #[derive(Debug, Eq, PartialEq, Hash)]
+    #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
     struct Edge([FloatOrd; 3], [FloatOrd; 3]);
-    // The two points of the edge mapped to the other vertices of the triangles the edge is part of.
+    /// One of the triangles an edge is part of.
+    #[derive(Debug, Clone, Copy)]
+    struct Side {
+        /// The vertex of the triangle that isn't part of the edge.
+        other_point: Vec3,
+        /// The vertex normals at the two points of the edge, in the order of the edge.
+        normals: Option<[Vec3; 2]>,
+        face_normal: Vec3,
+    }
+    let point = |p: [FloatOrd; 3]| Vec3::new(p[0].0, p[1].0, p[2].0);
+    // The two points of the edge mapped to the triangles the edge is part of.
     // The two points of the edge are ordered by x, y, z.
-    let mut edges = HashMap::<Edge, (Vec3, Option<Vec3>)>::new();
+    let mut edges = HashMap::<Edge, Vec<Side>>::new();
 
-    while let (Some(a), Some(b), Some(c)) = (
-        indices_iter.next(),
-        indices_iter.next(),
-        indices_iter.next(),
-    ) {
-        let abc = [
-            vertices[a].map(FloatOrd),
-            vertices[b].map(FloatOrd),
-            vertices[c].map(FloatOrd),
-        ];
+    for triangle in indices.chunks_exact(3) {
+        // Snapped to a grid, so duplicated vertices along uv seams are merged even if their
+        // positions differ slightly.
+        let abc = [0, 1, 2].map(|i| {
+            vertices[triangle[i]].map(|x| FloatOrd((x / WELD_DISTANCE).round() * WELD_DISTANCE))
+        });
+        let [a, b, c] = abc.map(point);
+        let face_normal = (b - a).cross(c - a).normalize_or_zero();
         for i in 0..3 {
-            let a = abc[i];
-            let b = abc[(i + 1) % 3];
-            let edge = Edge(a.min(b), a.max(b));
-            let c = abc[(i + 2) % 3];
-            let c = Vec3::new(c[0].0, c[1].0, c[2].0);
-            // println!("{:?}", edge);
-
-            if let Some(other_points) = edges.get_mut(&edge) {
-                assert!(other_points.1.is_none());
-                if other_points.0 != c {
-                    other_points.1 = Some(c);
-                }
+            let (a, b) = (abc[i], abc[(i + 1) % 3]);
+            let (ia, ib) = (triangle[i], triangle[(i + 1) % 3]);
+            let (edge, ends) = if a <= b {
+                (Edge(a, b), [ia, ib])
             } else {
-                edges.insert(edge, (c, None));
+                (Edge(b, a), [ib, ia])
+            };
+            let other_point = point(abc[(i + 2) % 3]);
+
+            let sides = edges.entry(edge).or_default();
+            // The same triangle twice, e.g. a double sided face, doesn't add another face.
+            if !sides.iter().any(|side| side.other_point == other_point) {
+                sides.push(Side {
+                    other_point,
+                    normals: normals.map(|n| ends.map(|i| Vec3::from(n[i]).normalize_or_zero())),
+                    face_normal,
+                });
             }
         }
     }
 
-    // println!("{:#?}", edges);
+    let bend = |edge: &Edge, [c, d]: [Side; 2]| {
+        let (a, b) = (point(edge.0), point(edge.1));
+        let tangent = tangent_of_edge((a, b), c.other_point);
+        let tangent2 = tangent_of_edge((a, b), d.other_point);
+        PI - tangent.angle_between(tangent2)
+    };
+    let bends: HashMap<Edge, f32> = edges
+        .iter()
+        .filter_map(|(edge, sides)| match sides[..] {
+            [c, d] => Some((*edge, bend(edge, [c, d]))),
+            _ => None,
+        })
+        .collect();
+    let edge_bend = |a: Vec3, b: Vec3| {
+        let (a, b) = (a.to_array().map(FloatOrd), b.to_array().map(FloatOrd));
+        bends.get(&Edge(a.min(b), a.max(b))).copied().unwrap_or(0.0)
+    };
 
+    let same = |a: Vec3, b: Vec3| a.angle_between(b) < SMOOTH_NORMAL_ANGLE;
     edges
-        .into_iter()
-        .map(|(Edge(a, b), (c, d))| {
-            let (a, b) = (
-                Vec3::new(a[0].0, a[1].0, a[2].0),
-                Vec3::new(b[0].0, b[1].0, b[2].0),
-            );
-
-            let angle = if let Some(d) = d {
-                let tangent = tangent_of_edge((a, b), c);
-                let tangent2 = tangent_of_edge((a, b), d);
-                Some(tangent.angle_between(tangent2))
-            } else {
-                None
+        .iter()
+        .map(|(edge, sides)| {
+            let (a, b) = (point(edge.0), point(edge.1));
+
+            let kind = match sides[..] {
+                [_] => EdgeKind::Boundary,
+                [c, d] => {
+                    let bend = bends[edge];
+                    let flat_shaded = |side: Side| {
+                        side.normals
+                            .is_none_or(|n| n.iter().all(|&n| same(n, side.face_normal)))
+                    };
+                    let smooth = match (c.normals, d.normals) {
+                        // Smooth shading shares the normals between the faces, a crease splits them.
+                        (Some([c0, c1]), Some([d0, d1])) if !flat_shaded(c) || !flat_shaded(d) => {
+                            same(c0, d0) && same(c1, d1)
+                        }
+                        // Otherwise a crease bends much more than the edges around it.
+                        _ => {
+                            let neighbors = [c, d]
+                                .into_iter()
+                                .flat_map(|side| [(a, side.other_point), (b, side.other_point)])
+                                .map(|(a, b)| edge_bend(a, b))
+                                .fold(0.0, f32::max);
+                            bend < MAX_SMOOTH_BEND && bend <= neighbors * CREASE_BEND_RATIO
+                        }
+                    };
+                    EdgeKind::Manifold {
+                        angle: PI - bend,
+                        smooth,
+                        normals: [c.face_normal, d.face_normal],
+                    }
+                }
+                _ => EdgeKind::NonManifold,
             };
 
-            (a, b, angle)
+            (a, b, kind)
         })
         .collect()
 }
 
+/// Ridges and valleys are only drawn where the surface bends more sharply than this, in 1/units.
+/// References are normalized to a radius of about 1.0, so a sphere the size of the reference
+/// bends by 1.0.
+const FEATURE_CURVATURE: f32 = 3.0;
+/// Suggestive contours are only drawn where the curvature towards the eye grows faster than this,
+/// which hides the noise of nearly flat areas.
+const SUGGESTIVE_MIN_DERIVATIVE: f32 = 1.0;
+/// Suggestive contours are only drawn where the surface is turned at least this far from the eye,
+/// as they continue the contours.
+const SUGGESTIVE_MIN_ANGLE: f32 = 20.0 * PI / 180.0;
+
+/// The meshes of a reference welded into one surface with the curvature at every vertex, for the
+/// feature lines of organic forms that have neither creases nor much of a silhouette.
+#[derive(Debug, Clone, Default)]
+pub struct Surface {
+    positions: Vec<Vec3>,
+    /// Smooth normals, ignoring the authored ones.
+    normals: Vec<Vec3>,
+    triangles: Vec<[usize; 3]>,
+    curvatures: Vec<Curvature>,
+    /// The lines along which the surface bends most, which don't depend on the view.
+    pub ridges_and_valleys: Vec<(Vec3, Vec3)>,
+}
+
+/// The principal curvatures at a vertex, positive where the surface is convex.
+#[derive(Debug, Clone, Copy, Default)]
+struct Curvature {
+    max: f32,
+    min: f32,
+    /// The tangent direction of `max`, `min` is perpendicular to it.
+    direction: Vec3,
+}
+
+impl Curvature {
+    /// The normal curvature along the tangent `direction`.
+    fn along(&self, direction: Vec3) -> f32 {
+        let cos = direction.normalize_or_zero().dot(self.direction);
+        self.max * cos * cos + self.min * (1.0 - cos * cos)
+    }
+}
+
+impl Surface {
+    /// Skips meshes that are not triangle lists.
+    pub fn new<'a>(meshes: impl IntoIterator<Item = &'a Mesh>) -> Surface {
+        let mut surface = Surface::default();
+        let mut welded = HashMap::<[FloatOrd; 3], usize>::new();
+        for mesh in meshes {
+            let Some(vertices) = mesh
+                .attribute(Mesh::ATTRIBUTE_POSITION)
+                .and_then(|p| p.as_float3())
+            else {
+                continue;
+            };
+            if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+                continue;
+            }
+            let indices: Vec<usize> = match mesh.indices() {
+                Some(indices) => indices.iter().collect(),
+                None => (0..vertices.len()).collect(),
+            };
+            for triangle in indices.chunks_exact(3) {
+                let triangle = [0, 1, 2].map(|i| {
+                    let p = vertices[triangle[i]]
+                        .map(|x| FloatOrd((x / WELD_DISTANCE).round() * WELD_DISTANCE));
+                    *welded.entry(p).or_insert_with(|| {
+                        surface.positions.push(Vec3::new(p[0].0, p[1].0, p[2].0));
+                        surface.positions.len() - 1
+                    })
+                });
+                // Degenerate triangles, e.g. at the poles of a uv sphere.
+                if triangle[0] != triangle[1]
+                    && triangle[1] != triangle[2]
+                    && triangle[2] != triangle[0]
+                {
+                    surface.triangles.push(triangle);
+                }
+            }
+        }
+
+        surface.normals = vec![Vec3::ZERO; surface.positions.len()];
+        for triangle in surface.triangles.iter() {
+            let [a, b, c] = triangle.map(|i| surface.positions[i]);
+            // Weighted by area.
+            let normal = (b - a).cross(c - a);
+            for &i in triangle {
+                surface.normals[i] += normal;
+            }
+        }
+        for normal in surface.normals.iter_mut() {
+            *normal = normal.normalize_or_zero();
+        }
+
+        surface.curvatures = surface.vertex_curvatures();
+        surface.ridges_and_valleys = surface.ridges_and_valleys();
+        surface
+    }
+
+    /// Fits the curvature at each vertex to the normal curvatures along its edges.
+    fn vertex_curvatures(&self) -> Vec<Curvature> {
+        // The least squares fit of the second fundamental form [[a, b], [b, c]] in a tangent
+        // frame of each vertex, as the normal equations of `(a, b, c)`.
+        let mut equations = vec![(Mat3::ZERO, Vec3::ZERO); self.positions.len()];
+        for triangle in self.triangles.iter() {
+            for k in 0..3 {
+                for (i, j) in [
+                    (triangle[k], triangle[(k + 1) % 3]),
+                    (triangle[(k + 1) % 3], triangle[k]),
+                ] {
+                    let (n, edge) = (self.normals[i], self.positions[j] - self.positions[i]);
+                    let curvature = (self.normals[j] - n).dot(edge) / edge.length_squared();
+                    let tangent = (edge - n * n.dot(edge)).normalize_or_zero();
+                    let (u, v) = n.any_orthonormal_pair();
+                    let (x, y) = (tangent.dot(u), tangent.dot(v));
+                    let row = Vec3::new(x * x, 2.0 * x * y, y * y);
+                    let (ata, atb) = &mut equations[i];
+                    *ata += Mat3::from_cols(row * row.x, row * row.y, row * row.z);
+                    *atb += row * curvature;
+                }
+            }
+        }
+
+        equations
+            .into_iter()
+            .zip(self.normals.iter())
+            .map(|((ata, atb), n)| {
+                if ata.determinant().abs() < 1e-9 {
+                    return Curvature::default();
+                }
+                let [a, b, c] = (ata.inverse() * atb).to_array();
+                let (mean, difference) = ((a + c) / 2.0, ((a - c) / 2.0).hypot(b));
+                let angle = 0.5 * (2.0 * b).atan2(a - c);
+                let (u, v) = n.any_orthonormal_pair();
+                Curvature {
+                    max: mean + difference,
+                    min: mean - difference,
+                    direction: u * angle.cos() + v * angle.sin(),
+                }
+            })
+            .collect()
+    }
+
+    /// Where the maximum curvature peaks across the surface (ridges) or the minimum curvature
+    /// dips (valleys), i.e. where its derivative along its own direction is zero.
+    fn ridges_and_valleys(&self) -> Vec<(Vec3, Vec3)> {
+        let directions: [Vec<Vec3>; 2] = [
+            self.curvatures.iter().map(|c| c.direction).collect(),
+            (self.curvatures.iter().zip(self.normals.iter()))
+                .map(|(c, n)| n.cross(c.direction))
+                .collect(),
+        ];
+        let values: [Vec<f32>; 2] = [
+            self.curvatures.iter().map(|c| c.max).collect(),
+            self.curvatures.iter().map(|c| c.min).collect(),
+        ];
+
+        let mut lines = Vec::new();
+        for (ridge, (directions, values)) in [true, false]
+            .into_iter()
+            .zip(directions.iter().zip(values.iter()))
+        {
+            // The derivative of the curvature along its direction at each vertex, averaging the
+            // gradients of the faces around it.
+            let mut gradients = vec![Vec3::ZERO; self.positions.len()];
+            for triangle in self.triangles.iter() {
+                let points = triangle.map(|i| self.positions[i]);
+                let area = (points[1] - points[0])
+                    .cross(points[2] - points[0])
+                    .length();
+                let gradient = triangle_gradient(points, triangle.map(|i| values[i]));
+                for &i in triangle {
+                    gradients[i] += gradient * area;
+                }
+            }
+
+            for triangle in self.triangles.iter() {
+                let points = triangle.map(|i| self.positions[i]);
+                let curvature = triangle.map(|i| values[i]).iter().sum::<f32>() / 3.0;
+                let other = triangle
+                    .map(|i| {
+                        if ridge {
+                            self.curvatures[i].min
+                        } else {
+                            self.curvatures[i].max
+                        }
+                    })
+                    .iter()
+                    .sum::<f32>()
+                    / 3.0;
+                let feature = match ridge {
+                    true => curvature > FEATURE_CURVATURE && curvature > other.abs(),
+                    false => curvature < -FEATURE_CURVATURE && -curvature > other.abs(),
+                };
+                if !feature {
+                    continue;
+                }
+                // The directions have no sign, so they are aligned with the first vertex's.
+                let direction = directions[triangle[0]];
+                let derivatives = triangle.map(|i| {
+                    let aligned = directions[i] * directions[i].dot(direction).signum();
+                    gradients[i].dot(aligned)
+                });
+                // A maximum for ridges, a minimum for valleys.
+                let change = triangle_gradient(points, derivatives).dot(direction);
+                if (change < 0.0) != ridge {
+                    continue;
+                }
+                lines.extend(zero_crossing(points, derivatives));
+            }
+        }
+        lines
+    }
+
+    /// The lines where the surface would become a contour from a nearby view, continuing the
+    /// contours into the surface. `towards_eye` is the direction from a point towards the eye.
+    pub fn suggestive_contours(&self, towards_eye: impl Fn(Vec3) -> Vec3) -> Vec<(Vec3, Vec3)> {
+        let views: Vec<Vec3> = self
+            .positions
+            .iter()
+            .map(|&p| towards_eye(p).normalize_or_zero())
+            .collect();
+        // The curvature in the direction of the eye projected onto the surface.
+        let radial: Vec<f32> = (0..self.positions.len())
+            .map(|i| {
+                let (n, view) = (self.normals[i], views[i]);
+                self.curvatures[i].along(view - n * n.dot(view))
+            })
+            .collect();
+
+        self.triangles
+            .iter()
+            .filter_map(|triangle| {
+                let points = triangle.map(|i| self.positions[i]);
+                let facing = triangle.map(|i| self.normals[i].dot(views[i]));
+                if facing
+                    .iter()
+                    .any(|&f| f <= 0.0 || f > SUGGESTIVE_MIN_ANGLE.cos())
+                {
+                    return None;
+                }
+                let values = triangle.map(|i| radial[i]);
+                let normal = (points[1] - points[0])
+                    .cross(points[2] - points[0])
+                    .normalize_or_zero();
+                let view = views[triangle[0]];
+                let towards_eye = (view - normal * normal.dot(view)).normalize_or_zero();
+                // Only where the curvature grows towards the eye, otherwise the zeros are
+                // between a contour and a hidden part of the surface.
+                if triangle_gradient(points, values).dot(towards_eye) < SUGGESTIVE_MIN_DERIVATIVE {
+                    return None;
+                }
+                zero_crossing(points, values)
+            })
+            .collect()
+    }
+}
+
+/// The gradient of the linear interpolation of `values` over the triangle.
+fn triangle_gradient([a, b, c]: [Vec3; 3], [fa, fb, fc]: [f32; 3]) -> Vec3 {
+    let normal = (b - a).cross(c - a);
+    let double_area = normal.length();
+    if double_area < 1e-12 {
+        return Vec3::ZERO;
+    }
+    let normal = normal / double_area;
+    ((fb - fa) * normal.cross(a - c) + (fc - fa) * normal.cross(b - a)) / double_area
+}
+
+/// Where the linear interpolation of `values` over the triangle crosses zero.
+fn zero_crossing(points: [Vec3; 3], values: [f32; 3]) -> Option<(Vec3, Vec3)> {
+    let mut crossings = (0..3).filter_map(|i| {
+        let (a, b) = (values[i], values[(i + 1) % 3]);
+        ((a < 0.0) != (b < 0.0)).then(|| points[i].lerp(points[(i + 1) % 3], a / (a - b)))
+    });
+    Some((crossings.next()?, crossings.next()?))
+}
+
 fn tangent_of_edge(edge: (Vec3, Vec3), other_point: Vec3) -> Vec3 {
     let (a, b) = edge;
     let ab = b - a;
@@ -382,237 +1165,274 @@ fn tangent_of_edge2(edge: (Vec3, Vec3), other_point: Vec3) -> Vec3 {
     (c - d).normalize()
 }
 
-#[derive(Resource)]
-struct Timer {
-    text_entity: Entity,
-    start: Instant,
-    interval: Duration,
-    paused: Option<Duration>,
-    hide: bool,
-    adjusting_interval: bool,
-}
-
-impl Timer {
-    fn time(&mut self) -> (Duration, bool) {
-        if self.adjusting_interval {
-            return (self.interval, false);
-        }
+const SCALING_BOUND_LOWER_LOG: f32 = -1.2;
+const SCALING_BOUND_UPPER_LOG: f32 = 1.2;
 
-        if let Some(paused) = self.paused {
-            (paused, false)
-        } else {
-            let mut elapsed = self.start.elapsed();
-            let reset = elapsed >= self.interval;
-            if reset {
-                self.start += self.interval;
-                elapsed = self.start.elapsed();
-            }
+#[derive(Resource, Debug, Clone)]
+pub struct ScaleSettings {
+    pub enabled: bool,
+    /// Scale all axes by the same factor.
+    pub uniform: bool,
+    /// Bounds of the scale factor, sampled uniformly in log space.
+    pub min: f32,
+    pub max: f32,
+}
 
-            (elapsed, reset)
+impl Default for ScaleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            uniform: false,
+            min: SCALING_BOUND_LOWER_LOG.exp2(),
+            max: SCALING_BOUND_UPPER_LOG.exp2(),
         }
     }
+}
 
-    fn toggle_hide(&mut self) {
-        self.hide = !self.hide;
-    }
-
-    fn is_paused(&self) -> bool {
-        self.paused.is_some()
-    }
-
-    fn toggle_pause(&mut self) {
-        self.set_pause(!self.is_paused());
-    }
+impl ScaleSettings {
+    pub fn sample(&self, rng: &mut impl Rng) -> Vec3 {
+        if !self.enabled {
+            return Vec3::ONE;
+        }
 
-    fn set_pause(&mut self, paused: bool) {
-        match (self.paused, paused) {
-            (None, true) => {
-                self.paused = Some(self.start.elapsed());
-            }
-            (Some(paused), false) => {
-                self.start = Instant::now() - paused;
-                self.paused = None;
-            }
-            _ => {}
+        let (min_log, max_log) = (self.min.log2(), self.max.log2());
+        let mut factor = || (rng.gen::<f32>() * (max_log - min_log) + min_log).exp2();
+        if self.uniform {
+            Vec3::splat(factor())
+        } else {
+            Vec3::new(factor(), factor(), factor())
         }
     }
 }
 
-#[derive(Component)]
-struct TimerText;
-
-#[derive(Event)]
-struct TimerEvent;
-
-fn update_timer(
-    mut timer: ResMut<Timer>,
-    mut query: Query<&mut Text, With<TimerText>>,
-    mut timer_writer: EventWriter<TimerEvent>,
-) {
-    let (elapsed, reset) = timer.time();
-    if reset {
-        timer_writer.send(TimerEvent);
-    }
+#[test]
+fn test_edge_angles() {
+    use std::f32::consts::FRAC_PI_2;
+
+    use bevy::render::{mesh::Indices, render_asset::RenderAssetUsages};
+
+    // Two triangles folded by 90° along the x axis.
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+        ],
+    );
+    mesh.insert_indices(Indices::U32(vec![0, 1, 2, 1, 0, 3]));
+
+    let edges = edge_angles(&mesh);
+    assert_eq!(edges.len(), 5);
+    let kinds = |kind: fn(&EdgeKind) -> bool| edges.iter().filter(|(_, _, k)| kind(k)).count();
+    assert_eq!(kinds(|k| *k == EdgeKind::Boundary), 4);
+    assert_eq!(
+        kinds(|k| matches!(
+            k,
+            EdgeKind::Manifold { angle, smooth: false, .. } if (angle - FRAC_PI_2).abs() < 1e-5
+        )),
+        1
+    );
+}
 
-    let text = if timer.hide {
-        "".to_string()
-    } else {
-        format!("{:05.2}", elapsed.as_secs_f32())
+#[test]
+fn test_adaptive_creases() {
+    let mut mesh = Mesh::from(Cylinder::new(1.0, 2.0).mesh().resolution(24));
+    let creases = |mesh: &Mesh| {
+        edge_angles(mesh)
+            .into_iter()
+            .filter(|(_, _, kind)| matches!(kind, EdgeKind::Manifold { smooth: false, .. }))
+            .count()
     };
-    query.get_mut(timer.text_entity).unwrap().sections[0].value = text;
+    // Only the rims of the caps, using the normals.
+    assert_eq!(creases(&mesh), 48);
+
+    // And using the curvature, when the normals are the same as the face normals.
+    mesh.duplicate_vertices();
+    mesh.compute_flat_normals();
+    assert_eq!(creases(&mesh), 48);
 }
 
-const UI_RENDER_LAYER: RenderLayers = RenderLayers::layer(1);
+#[test]
+fn test_surface_curvature() {
+    let sphere = Sphere::new(0.5).mesh().ico(3).unwrap();
+    let surface = Surface::new([&sphere]);
+    for curvature in surface.curvatures.iter() {
+        assert!((curvature.max - 2.0).abs() < 0.1, "{curvature:?}");
+        assert!((curvature.min - 2.0).abs() < 0.1, "{curvature:?}");
+    }
+    // A sphere bends the same everywhere.
+    assert!(surface.ridges_and_valleys.is_empty());
+    assert!(surface.suggestive_contours(|_| Vec3::Z).is_empty());
+}
 
-fn setup_timer(mut commands: Commands) {
-    // ui camera
-    commands.spawn((
-        Camera2dBundle {
-            camera: Camera {
-                order: 10000,
-                ..default()
-            },
-            ..default()
-        },
-        UI_RENDER_LAYER,
+#[test]
+fn test_surface_features() {
+    use std::f32::consts::{FRAC_PI_4, FRAC_PI_8};
+
+    use bevy::render::{mesh::Indices, render_asset::RenderAssetUsages};
+
+    // A height field with a ridge along x = 0 between valleys at x = ±π/4, bending by 4.0.
+    let height = |x: f32| 0.25 * (4.0 * x).cos();
+    let n = 60;
+    let coordinate = |i: u32| 2.0 * i as f32 / n as f32 - 1.0;
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        (0..=n)
+            .flat_map(|j| (0..=n).map(move |i| (coordinate(i), coordinate(j))))
+            .map(|(x, y)| [x, y, height(x)])
+            .collect::<Vec<_>>(),
+    );
+    mesh.insert_indices(Indices::U32(
+        (0..n)
+            .flat_map(|j| (0..n).map(move |i| j * (n + 1) + i))
+            .flat_map(|k| [k, k + 1, k + n + 2, k, k + n + 2, k + n + 1])
+            .collect(),
     ));
+    let surface = Surface::new([&mesh]);
+    // Every line away from the border is at one of `xs`, and there is one at each of them.
+    let assert_lines_at = |lines: &[(Vec3, Vec3)], xs: &[f32]| {
+        let lines: Vec<f32> = lines
+            .iter()
+            .map(|&(a, b)| (a + b) / 2.0)
+            .filter(|p| p.x.abs() < 0.9 && p.y.abs() < 0.9)
+            .map(|p| p.x)
+            .collect();
+        for line in lines.iter() {
+            assert!(xs.iter().any(|x| (line - x).abs() < 0.02), "{line}");
+        }
+        for x in xs {
+            assert!(lines.iter().any(|line| (line - x).abs() < 0.02), "{x}");
+        }
+    };
 
-    let mut text_entity = Entity::PLACEHOLDER;
-
-    commands
-        .spawn((
-            NodeBundle {
-                style: Style {
-                    width: Val::Percent(100.0),
-                    height: Val::Percent(100.0),
-                    align_items: AlignItems::End,
-                    justify_content: JustifyContent::End,
-                    ..default()
-                },
-                ..default()
-            },
-            UI_RENDER_LAYER,
-        ))
+    assert_lines_at(&surface.ridges_and_valleys, &[-FRAC_PI_4, 0.0, FRAC_PI_4]);
+    // Seen from above, where the ridge turns into the valleys.
+    assert_lines_at(
+        &surface.suggestive_contours(|_| Vec3::Z),
+        &[-FRAC_PI_8, FRAC_PI_8],
+    );
+}
+
+#[test]
+fn test_normalization() {
+    let mut world = World::new();
+    let mut node = Entity::PLACEHOLDER;
+    world
+        .spawn(Transform::from_xyz(12.0, 0.0, 0.0))
         .with_children(|parent| {
-            parent
-                .spawn((
-                    ButtonBundle {
-                        style: Style {
-                            width: Val::Px(150.0),
-                            height: Val::Px(65.0),
-                            border: UiRect::all(Val::Px(5.0)),
-                            // horizontally center child text
-                            justify_content: JustifyContent::Center,
-                            // vertically center child text
-                            align_items: AlignItems::Center,
-                            ..default()
-                        },
-                        border_color: BorderColor(Color::BLACK),
-                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-
-                        ..default()
-                    },
-                    On::<PointerEvent>::run(timer_interaction),
-                ))
-                .with_children(|parent| {
-                    text_entity = parent
-                        .spawn((
-                            TextBundle::from_section(
-                                "",
-                                TextStyle {
-                                    font: Handle::default(),
-                                    font_size: 40.0,
-                                    color: Color::rgb(0.9, 0.9, 0.9),
-                                },
-                            ),
-                            TimerText,
-                        ))
-                        .id();
-                });
+            node = parent
+                .spawn(Transform::from_xyz(-2.0, 0.0, 0.0).with_scale(Vec3::splat(2.0)))
+                .id();
         });
+    let node_transform = scene_transform(&world, node);
+    assert_eq!(node_transform.translation, Vec3::new(10.0, 0.0, 0.0));
+
+    // Two cubes from x = 9 to 15 in the scene, one of them placed by its node.
+    let cube = Mesh::from(Cuboid::default());
+    let meshes = [
+        cube.clone().transformed_by(node_transform),
+        cube.transformed_by(Transform::from_xyz(14.0, 0.0, 0.0).with_scale(Vec3::splat(2.0))),
+    ];
+    let normalization = Normalization::default().transform(&meshes);
+    let corner = normalization.transform_point(Vec3::new(15.0, 1.0, 1.0));
+    assert!((corner.length() - 1.0).abs() < 1e-5, "{corner}");
+    assert!(
+        normalization
+            .transform_point(Vec3::new(12.0, 0.0, 0.0))
+            .length()
+            < 1e-5
+    );
+}
 
-    commands.insert_resource(Timer {
-        text_entity,
-        start: Instant::now(),
-        paused: None,
-        interval: std::time::Duration::from_secs_f32(TIMER_INTERVAL),
-        hide: false,
-        adjusting_interval: false,
-    });
+#[test]
+fn test_wireframe() {
+    let reference = Reference {
+        edges: edge_angles(&Mesh::from(Cuboid::default())),
+        ..test_reference("cube", Entity::PLACEHOLDER)
+    };
+    // Without the diagonals of the faces.
+    assert_eq!(reference.edges.len(), 18);
+    assert_eq!(reference.wireframe().count(), 12);
 }
 
-fn timer_interaction(
-    mut timer: ResMut<Timer>,
-    mut wrapping_cursor: ResMut<NextState<WrappingCursorState>>,
-    mut wrap_events: EventReader<Wrap>,
-    event: Listener<PointerEvent>,
-) {
-    match &**event {
-        PointerEvent::DragStart(_) => {
-            timer.adjusting_interval = true;
-            timer.set_pause(true);
-            wrapping_cursor.set(WrappingCursorState::On);
-        }
-        PointerEvent::Drag(e) => {
-            // ignoring pointer wrapping. this is not an ideal solution as one could imagine that there is
-            // multiple Drag events in a single frame, but in practice that isn't the case in the current version
-            // of bevy_mod_picking.
-            if wrap_events.read().len() == 0 {
-                timer.interval = Duration::from_secs_f32(
-                    (timer.interval.as_secs_f32() + e.delta.x * 0.01).max(0.1),
-                );
-            }
-        }
-        PointerEvent::DragEnd(_) => {
-            timer.adjusting_interval = false;
-            timer.set_pause(false);
-            wrapping_cursor.set(WrappingCursorState::Off);
-        }
-        PointerEvent::Up(e) => {
-            if !timer.adjusting_interval {
-                match e.button {
-                    PointerButton::Primary => {
-                        timer.toggle_pause();
-                    }
-                    PointerButton::Secondary => {
-                        timer.toggle_hide();
-                    }
-                    _ => {}
-                }
-            }
-        }
-        _ => {}
-    }
+#[test]
+fn test_crease_angles_file() {
+    let angles: HashMap<String, CreaseAngles> = [
+        (
+            "references/cube.glb#Scene0".to_string(),
+            CreaseAngles {
+                adaptive: false,
+                min: 0.5,
+                max: 30.3f32.to_radians(),
+            },
+        ),
+        (
+            "references/heads.glb#Scene1".to_string(),
+            CreaseAngles {
+                adaptive: true,
+                ..default()
+            },
+        ),
+    ]
+    .into_iter()
+    .collect();
+    let text = format_crease_angles(&angles);
+    assert!(text.starts_with("0.5 "), "{text}");
+    assert_eq!(parse_crease_angles(&format!("{text}not a line\n")), angles);
 }
 
-fn random_rotation() -> Quat {
-    Quat::from_euler(
-        EulerRot::XYZ,
-        rand::random::<f32>() * std::f32::consts::PI * 2.0,
-        rand::random::<f32>() * std::f32::consts::PI * 2.0,
-        rand::random::<f32>() * std::f32::consts::PI * 2.0,
-    )
+/// A reference without any geometry, for tests.
+#[cfg(test)]
+pub fn test_reference(name: &str, entity: Entity) -> Reference {
+    Reference {
+        name: Name::new(name.to_string()),
+        path: name.to_string(),
+        entity,
+        edges: Vec::new(),
+        crease_angles: None,
+        triangles: Vec::new(),
+        surface: Surface::default(),
+        rotation: None,
+        outline: Handle::default(),
+        materials: Vec::new(),
+        radius: 1.0,
+    }
 }
 
 #[test]
-fn test_camera() {
-    let mut camera = Transform::default();
-}
+fn test_next_references() {
+    let mut refs = References {
+        references: ["a", "b", "c", "d"]
+            .map(|name| test_reference(name, Entity::PLACEHOLDER))
+            .to_vec(),
+        disabled_references: [1].into_iter().collect(),
+        current_reference: None,
+        shown: Vec::new(),
+        loading_folder: Handle::default(),
+    };
 
-const SCALING_BOUND_LOWER_LOG: f32 = -1.2;
-const SCALING_BOUND_UPPER_LOG: f32 = 1.2;
+    assert_eq!(refs.next_references(2), [0, 2]);
+    refs.shown = vec![0, 2];
+    assert_eq!(refs.next_references(2), [3, 0]);
+    // Never more than the active references.
+    assert_eq!(refs.next_references(10), [3, 0, 2]);
+
+    // Picking a reference continues after it, whatever is shown.
+    refs.current_reference = Some(0);
+    assert_eq!(refs.next_references(2), [3, 0]);
+    refs.set_current(3);
+    assert_eq!(refs.next_references(2), [0, 2]);
 
-fn random_scale(rng: &mut impl Rng) -> Vec3 {
-    let x_factor_log = rng.gen::<f32>() * (SCALING_BOUND_UPPER_LOG - SCALING_BOUND_LOWER_LOG)
-        + SCALING_BOUND_LOWER_LOG;
-    let y_factor_log = rng.gen::<f32>() * (SCALING_BOUND_UPPER_LOG - SCALING_BOUND_LOWER_LOG)
-        + SCALING_BOUND_LOWER_LOG;
-    let z_factor_log = rng.gen::<f32>() * (SCALING_BOUND_UPPER_LOG - SCALING_BOUND_LOWER_LOG)
-        + SCALING_BOUND_LOWER_LOG;
-
-    Vec3::new(
-        x_factor_log.exp2(),
-        y_factor_log.exp2(),
-        z_factor_log.exp2(),
-    )
+    refs.disabled_references = (0..4).collect();
+    assert!(refs.next_references(1).is_empty());
 }
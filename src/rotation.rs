@@ -0,0 +1,272 @@
+use std::f32::consts::TAU;
+use std::ops::RangeInclusive;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Above this threshold almost no orientation would be accepted.
+const MAX_VIEW_QUALITY_THRESHOLD: f32 = 0.7;
+/// How many orientations are tried before giving up and using the best one found.
+const MAX_ATTEMPTS: usize = 128;
+
+#[derive(Resource, Debug, Clone)]
+pub struct RotationSettings {
+    /// How strongly degenerate views are rejected in [`RotationMode::Free`]. At 0.0 every
+    /// orientation is accepted, at 1.0 only views looking roughly along a diagonal of the
+    /// reference's axes are.
+    pub strictness: f32,
+    /// Used for every reference without its own constraints.
+    pub constraints: RotationConstraints,
+}
+
+impl Default for RotationSettings {
+    fn default() -> Self {
+        Self {
+            strictness: 0.3,
+            constraints: default(),
+        }
+    }
+}
+
+impl RotationSettings {
+    /// Samples a random orientation for an object. `constraints` overrides the global ones.
+    /// `view_dir` points from the object towards the camera.
+    pub fn sample(
+        &self,
+        rng: &mut impl Rng,
+        view_dir: Vec3,
+        constraints: Option<&RotationConstraints>,
+    ) -> Quat {
+        let constraints = constraints.unwrap_or(&self.constraints);
+        match constraints.mode {
+            RotationMode::Free => self.sample_free(rng, view_dir),
+            RotationMode::Upright | RotationMode::Tilted => {
+                constraints.sample_constrained(rng, view_dir)
+            }
+        }
+    }
+
+    /// Samples a uniformly distributed orientation, rejecting ones with a bad [`view_quality`].
+    fn sample_free(&self, rng: &mut impl Rng, view_dir: Vec3) -> Quat {
+        let threshold = self.strictness.clamp(0.0, 1.0) * MAX_VIEW_QUALITY_THRESHOLD;
+
+        let mut best = (f32::NEG_INFINITY, Quat::IDENTITY);
+        for _ in 0..MAX_ATTEMPTS {
+            let rotation = uniform_rotation(rng);
+            let quality = view_quality(rotation, view_dir);
+            if quality >= threshold {
+                return rotation;
+            }
+            if quality > best.0 {
+                best = (quality, rotation);
+            }
+        }
+        best.1
+    }
+}
+
+/// Limits the orientations a reference can be shown in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotationConstraints {
+    pub mode: RotationMode,
+    /// The local axis of the reference that points up when it rests on the ground.
+    pub up: UpAxis,
+    /// The largest angle in radians between the up axis and the world up in [`RotationMode::Tilted`].
+    pub max_tilt: f32,
+    pub horizon: Horizon,
+    /// Rotation around the view direction in radians. Random within ±`max_roll` if `None`.
+    pub roll: Option<f32>,
+    /// The largest random roll in radians.
+    pub max_roll: f32,
+}
+
+impl Default for RotationConstraints {
+    fn default() -> Self {
+        Self {
+            mode: RotationMode::Free,
+            up: UpAxis::Y,
+            max_tilt: 30f32.to_radians(),
+            horizon: Horizon::Any,
+            roll: Some(0.0),
+            max_roll: 30f32.to_radians(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationMode {
+    /// Any orientation.
+    Free,
+    /// Resting on the ground, only rotated around the up axis.
+    Upright,
+    /// Like [`RotationMode::Upright`], but the up axis may tilt away from the world up.
+    Tilted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    pub fn vec(self) -> Vec3 {
+        match self {
+            UpAxis::X => Vec3::X,
+            UpAxis::Y => Vec3::Y,
+            UpAxis::Z => Vec3::Z,
+        }
+    }
+}
+
+/// Where the camera is relative to the reference's horizon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Horizon {
+    Any,
+    /// Looking down onto the reference.
+    Above,
+    EyeLevel,
+    /// Looking up at the reference.
+    Below,
+}
+
+impl Horizon {
+    /// Angle of the camera above the horizon in radians.
+    pub fn elevation_range(self) -> RangeInclusive<f32> {
+        let (min, max): (f32, f32) = match self {
+            Horizon::Any => (-50.0, 50.0),
+            Horizon::Above => (15.0, 50.0),
+            Horizon::EyeLevel => (-5.0, 5.0),
+            Horizon::Below => (-50.0, -15.0),
+        };
+        min.to_radians()..=max.to_radians()
+    }
+}
+
+impl RotationConstraints {
+    fn sample_constrained(&self, rng: &mut impl Rng, view_dir: Vec3) -> Quat {
+        let view_dir = view_dir.try_normalize().unwrap_or(Vec3::Z);
+        // The horizontal axis of the view, tilting around it changes the elevation.
+        let right = Vec3::Y.cross(view_dir).try_normalize().unwrap_or(Vec3::X);
+
+        let stand_up = Quat::from_rotation_arc(self.up.vec(), Vec3::Y);
+        let yaw = Quat::from_rotation_y(rng.gen_range(0.0..TAU));
+        let tilt = match self.mode {
+            RotationMode::Tilted => {
+                // Uniformly distributed over the cone's cap.
+                let cos_tilt = rng.gen_range(self.max_tilt.cos()..=1.0);
+                let axis = Quat::from_rotation_y(rng.gen_range(0.0..TAU)) * Vec3::X;
+                Quat::from_axis_angle(axis, cos_tilt.acos())
+            }
+            _ => Quat::IDENTITY,
+        };
+        let elevation = Quat::from_axis_angle(right, rng.gen_range(self.horizon.elevation_range()));
+        let roll = self
+            .roll
+            .unwrap_or_else(|| rng.gen_range(-self.max_roll..=self.max_roll));
+        let roll = Quat::from_axis_angle(view_dir, roll);
+
+        roll * elevation * tilt * yaw * stand_up
+    }
+}
+
+/// A rotation uniformly distributed over all orientations.
+/// See Ken Shoemake, "Uniform random rotations", Graphics Gems III.
+pub fn uniform_rotation(rng: &mut impl Rng) -> Quat {
+    let (u1, u2, u3) = (rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>());
+    let (a, b) = ((1.0 - u1).sqrt(), u1.sqrt());
+    let (theta1, theta2) = (TAU * u2, TAU * u3);
+
+    Quat::from_xyzw(
+        a * theta1.sin(),
+        a * theta1.cos(),
+        b * theta2.sin(),
+        b * theta2.cos(),
+    )
+    .normalize()
+}
+
+/// How much of the object's form is visible when looking at it along `view_dir`, from 0.0 to 1.0.
+///
+/// References are modelled aligned to their local axes, so looking along one of the axes shows
+/// a single face (a cylinder reads as a rectangle or a circle) and looking along one of the
+/// axis planes shows faces edge-on. The best view looks along a diagonal and shows three sides.
+pub fn view_quality(rotation: Quat, view_dir: Vec3) -> f32 {
+    let local = (rotation.inverse() * view_dir).normalize_or_zero().abs();
+    local.min_element() * 3f32.sqrt()
+}
+
+#[cfg(test)]
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn test_uniform_rotation_is_unbiased() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let n = 20_000;
+
+    let mut mean = Vec3::ZERO;
+    let mut along_axis = 0;
+    for _ in 0..n {
+        let v = uniform_rotation(&mut rng) * Vec3::Z;
+        assert!((v.length() - 1.0).abs() < 1e-4);
+        mean += v;
+        if v.z.abs() > 0.9 {
+            along_axis += 1;
+        }
+    }
+    mean /= n as f32;
+
+    assert!(mean.length() < 0.03, "mean {mean}");
+    // For uniformly distributed directions |z| is uniform, so 10% should point along the axis.
+    let fraction = along_axis as f32 / n as f32;
+    assert!((fraction - 0.1).abs() < 0.01, "fraction {fraction}");
+}
+
+#[test]
+fn test_view_quality() {
+    assert_eq!(view_quality(Quat::IDENTITY, Vec3::Z), 0.0);
+    assert!((view_quality(Quat::IDENTITY, Vec3::ONE.normalize()) - 1.0).abs() < 1e-5);
+
+    let settings = RotationSettings {
+        strictness: 1.0,
+        ..default()
+    };
+    let mut rng = StdRng::seed_from_u64(1);
+    for _ in 0..100 {
+        let rotation = settings.sample(&mut rng, Vec3::Z, None);
+        assert!(view_quality(rotation, Vec3::Z) >= MAX_VIEW_QUALITY_THRESHOLD);
+    }
+}
+
+#[test]
+fn test_constrained_rotation() {
+    let settings = RotationSettings::default();
+    let mut rng = StdRng::seed_from_u64(2);
+
+    let upright = RotationConstraints {
+        mode: RotationMode::Upright,
+        up: UpAxis::Z,
+        horizon: Horizon::EyeLevel,
+        ..default()
+    };
+    let tilted = RotationConstraints {
+        mode: RotationMode::Tilted,
+        max_tilt: 10f32.to_radians(),
+        horizon: Horizon::Above,
+        ..default()
+    };
+    for _ in 0..100 {
+        let rotation = settings.sample(&mut rng, Vec3::Z, Some(&upright));
+        let up = rotation * Vec3::Z;
+        assert!(up.angle_between(Vec3::Y) <= 5.01f32.to_radians(), "{up}");
+        // With a fixed roll of zero, the up axis stays vertical on screen.
+        assert!(up.x.abs() < 1e-4, "{up}");
+
+        let rotation = settings.sample(&mut rng, Vec3::Z, Some(&tilted));
+        let up = rotation * Vec3::Y;
+        assert!(up.angle_between(Vec3::Y) <= 60.01f32.to_radians(), "{up}");
+        // Seen from above, the top of the reference faces the camera.
+        assert!(up.z > 0.0, "{up}");
+    }
+}
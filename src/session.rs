@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// The poses drawn since the app was started.
+#[derive(Resource, Debug, Default)]
+pub struct Session {
+    pub poses: Vec<Pose>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pose {
+    pub reference: Name,
+    /// How long the pose was actually shown, excluding pauses.
+    pub duration: Duration,
+}
+
+impl Session {
+    pub fn total(&self) -> Duration {
+        self.poses.iter().map(|pose| pose.duration).sum()
+    }
+}
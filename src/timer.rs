@@ -0,0 +1,782 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy_mod_picking::prelude::*;
+use rand::Rng;
+
+use crate::picking_ext::PointerEvent;
+use crate::wrapping_cursor::{Wrap, WrappingCursorState};
+
+const TIMER_INTERVAL: f32 = 3.0;
+pub const MIN_INTERVAL: Duration = Duration::from_millis(100);
+pub const UI_RENDER_LAYER: RenderLayers = RenderLayers::layer(1);
+
+pub struct TimerPlugin;
+
+impl Plugin for TimerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TimerEvent>()
+            .add_event::<NextPose>()
+            .init_resource::<IntervalSettings>()
+            .init_resource::<TimerDisplay>()
+            .add_systems(Startup, setup_pose_timer)
+            .add_systems(
+                Update,
+                (
+                    (next_pose, tick_timers).chain(),
+                    randomize_pose_interval,
+                    (update_timer_text, update_timer_progress),
+                )
+                    .chain()
+                    .in_set(TimerSet),
+            );
+    }
+}
+
+/// Systems reacting to [`TimerEvent`] should run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TimerSet;
+
+/// A repeating timer driven by the virtual [`Time`], so pausing or slowing down the app also
+/// affects the timer. Sends a [`TimerEvent`] every time the interval elapses or [`NextPose`] is sent.
+#[derive(Component, Debug, Clone)]
+pub struct Timer {
+    elapsed: Duration,
+    interval: Duration,
+    paused: bool,
+    /// Time left until the timer continues after being resumed with a countdown.
+    resume_countdown: Option<Duration>,
+    pub mode: TimerMode,
+    pub hide: bool,
+    pub adjusting_interval: bool,
+    /// Stops the timer without pausing it, e.g. during the check phase, so a pause by the user or
+    /// an automatic pause in the meantime is kept.
+    pub held: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimerMode {
+    /// Fires every time the interval elapses.
+    #[default]
+    Interval,
+    /// Counts up without ever firing.
+    Stopwatch,
+    /// Like [`TimerMode::Stopwatch`], but the time isn't shown.
+    Untimed,
+}
+
+impl Timer {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            interval,
+            paused: false,
+            resume_countdown: None,
+            mode: TimerMode::Interval,
+            hide: false,
+            adjusting_interval: false,
+            held: false,
+        }
+    }
+
+    /// Advances the timer by `delta` and returns true if the interval elapsed.
+    /// The time exceeding the interval is carried over into the next interval.
+    pub fn tick(&mut self, mut delta: Duration) -> bool {
+        if self.paused || self.adjusting_interval || self.held {
+            return false;
+        }
+
+        if let Some(countdown) = self.resume_countdown {
+            if delta < countdown {
+                self.resume_countdown = Some(countdown - delta);
+                return false;
+            }
+            delta -= countdown;
+            self.resume_countdown = None;
+        }
+
+        self.elapsed += delta;
+        if self.mode != TimerMode::Interval {
+            return false;
+        }
+
+        if self.elapsed >= self.interval {
+            // Carry over the remainder instead of restarting at zero, so the timer doesn't drift.
+            // Only one event is fired even if the interval elapsed multiple times.
+            self.elapsed = Duration::from_nanos(
+                (self.elapsed.as_nanos() % self.interval.as_nanos().max(1)) as u64,
+            );
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The time to display. While the interval is being adjusted this is the interval itself.
+    pub fn elapsed(&self) -> Duration {
+        if self.adjusting_interval {
+            self.interval
+        } else {
+            self.elapsed
+        }
+    }
+
+    /// The time left in the current interval. While the interval is being adjusted this is the
+    /// interval itself.
+    pub fn remaining(&self) -> Duration {
+        if self.adjusting_interval {
+            self.interval
+        } else {
+            self.interval.saturating_sub(self.elapsed)
+        }
+    }
+
+    /// Remaining time relative to the interval, from 1.0 at the start to 0.0 at the end.
+    pub fn fraction_remaining(&self) -> f32 {
+        if self.adjusting_interval {
+            return 1.0;
+        }
+        (self.remaining().as_secs_f32() / self.interval.as_secs_f32()).clamp(0.0, 1.0)
+    }
+
+    /// Whether the interval is about to end, i.e. less than `warning` is remaining.
+    pub fn in_warning(&self, warning: Duration) -> bool {
+        self.mode == TimerMode::Interval
+            && !self.adjusting_interval
+            && !warning.is_zero()
+            && self.remaining() <= warning
+    }
+
+    /// Ends the current interval early and returns how long it lasted.
+    pub fn skip(&mut self) -> Duration {
+        let elapsed = self.elapsed;
+        self.elapsed = Duration::ZERO;
+        self.resume_countdown = None;
+        elapsed
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval.max(MIN_INTERVAL);
+    }
+
+    pub fn toggle_hide(&mut self) {
+        self.hide = !self.hide;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.set_pause(!self.is_paused());
+    }
+
+    pub fn set_pause(&mut self, paused: bool) {
+        self.paused = paused;
+        self.resume_countdown = None;
+    }
+
+    /// Unpauses the timer, but only continues once `countdown` has passed.
+    pub fn resume_with_countdown(&mut self, countdown: Duration) {
+        self.set_pause(false);
+        if !countdown.is_zero() {
+            self.resume_countdown = Some(countdown);
+        }
+    }
+
+    pub fn resume_countdown(&self) -> Option<Duration> {
+        self.resume_countdown
+    }
+}
+
+/// How the interval of the [`PoseTimer`] is chosen for every pose.
+#[derive(Resource, Debug, Clone)]
+pub struct IntervalSettings {
+    /// Pick a random interval between `min` and `max` for every pose.
+    pub random: bool,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl Default for IntervalSettings {
+    fn default() -> Self {
+        Self {
+            random: false,
+            min: Duration::from_secs(30),
+            max: Duration::from_secs(120),
+        }
+    }
+}
+
+impl IntervalSettings {
+    pub const PRESETS: [(&'static str, Option<Duration>); 6] = [
+        ("30s", Some(Duration::from_secs(30))),
+        ("1m", Some(Duration::from_secs(60))),
+        ("2m", Some(Duration::from_secs(2 * 60))),
+        ("5m", Some(Duration::from_secs(5 * 60))),
+        ("10m", Some(Duration::from_secs(10 * 60))),
+        ("untimed", None),
+    ];
+
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        let (min, max) = (self.min.as_secs_f32(), self.max.as_secs_f32());
+        if min >= max {
+            return self.min;
+        }
+        Duration::from_secs_f32(rng.gen_range(min..=max))
+    }
+}
+
+/// How the [`PoseTimer`] is displayed.
+#[derive(Resource, Debug, Clone)]
+pub struct TimerDisplay {
+    pub time: TimeDisplay,
+    /// During the last `warning` of an interval the timer turns red and pulses. Zero disables it.
+    pub warning: Duration,
+}
+
+impl Default for TimerDisplay {
+    fn default() -> Self {
+        Self {
+            time: TimeDisplay::Remaining,
+            warning: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeDisplay {
+    Remaining,
+    Elapsed,
+}
+
+const TIMER_TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const TIMER_PROGRESS_COLOR: Color = Color::rgb(0.5, 0.5, 0.5);
+const TIMER_WARNING_COLOR: Color = Color::rgb(0.9, 0.15, 0.15);
+
+/// Formats the time shown on the timer. Intervals of a minute or longer are shown as `mm:ss`,
+/// shorter ones as seconds with hundredths. A countdown is rounded up (`round_up`), so it only
+/// shows zero once the time is up.
+pub fn format_timer_time(time: Duration, interval: Duration, round_up: bool) -> String {
+    let long = interval >= Duration::from_secs(60);
+    let unit = if long {
+        Duration::from_secs(1)
+    } else {
+        Duration::from_millis(10)
+    }
+    .as_nanos();
+    let units = if round_up {
+        time.as_nanos().div_ceil(unit)
+    } else {
+        time.as_nanos() / unit
+    };
+    let time = Duration::from_nanos((units * unit) as u64);
+    if long {
+        format_minutes_seconds(time)
+    } else {
+        format!("{:05.2}", time.as_secs_f32())
+    }
+}
+
+/// Formats the duration as `mm:ss`, e.g. `02:30`, with tenths of a second if there are any, e.g.
+/// `00:00.1`.
+pub fn format_minutes_seconds(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match duration.subsec_millis() / 100 {
+        0 => format!("{:02}:{:02}", seconds / 60, seconds % 60),
+        tenths => format!("{:02}:{:02}.{}", seconds / 60, seconds % 60, tenths),
+    }
+}
+
+/// Parses either `mm:ss` or a plain number of seconds.
+pub fn parse_minutes_seconds(text: &str) -> Option<f64> {
+    match text.trim().split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes = minutes.trim().parse::<f64>().ok()?;
+            let seconds = seconds.trim().parse::<f64>().ok()?;
+            Some(minutes * 60.0 + seconds)
+        }
+        None => text.trim().parse::<f64>().ok(),
+    }
+}
+
+/// Marks the timer that decides when the next reference is shown.
+#[derive(Component)]
+pub struct PoseTimer;
+
+/// Displays the time of the [`Timer`] on its parent entity.
+#[derive(Component)]
+struct TimerText;
+
+/// A bar shrinking with the remaining time of the [`Timer`] on its parent entity.
+#[derive(Component)]
+struct TimerProgress;
+
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerEvent {
+    pub timer: Entity,
+    /// How long the interval that just ended lasted.
+    pub elapsed: Duration,
+}
+
+/// Skips to the next pose by ending the interval of the [`PoseTimer`] early.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct NextPose;
+
+fn tick_timers(
+    time: Res<Time<Virtual>>,
+    mut next_events: EventReader<NextPose>,
+    mut timers: Query<(Entity, &mut Timer, Has<PoseTimer>)>,
+    mut timer_writer: EventWriter<TimerEvent>,
+) {
+    // A pose timer that was just skipped by `next_pose` starts counting on the next frame, so it
+    // can't elapse in the same frame and skip a second pose.
+    let skipped = next_events.read().count() > 0;
+    for (entity, mut timer, pose_timer) in timers.iter_mut() {
+        if skipped && pose_timer {
+            continue;
+        }
+        if timer.tick(time.delta()) {
+            timer_writer.send(TimerEvent {
+                timer: entity,
+                elapsed: timer.interval(),
+            });
+        }
+    }
+}
+
+fn next_pose(
+    mut next_events: EventReader<NextPose>,
+    mut timers: Query<(Entity, &mut Timer), With<PoseTimer>>,
+    mut timer_writer: EventWriter<TimerEvent>,
+) {
+    if next_events.read().count() == 0 {
+        return;
+    }
+
+    for (entity, mut timer) in timers.iter_mut() {
+        timer_writer.send(TimerEvent {
+            timer: entity,
+            elapsed: timer.skip(),
+        });
+    }
+}
+
+fn randomize_pose_interval(
+    settings: Res<IntervalSettings>,
+    mut timer_events: EventReader<TimerEvent>,
+    mut timers: Query<&mut Timer, With<PoseTimer>>,
+) {
+    if !settings.random {
+        timer_events.clear();
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    for event in timer_events.read() {
+        if let Ok(mut timer) = timers.get_mut(event.timer) {
+            timer.set_interval(settings.sample(&mut rng));
+        }
+    }
+}
+
+fn update_timer_text(
+    display: Res<TimerDisplay>,
+    timers: Query<&Timer>,
+    mut query: Query<(&mut Text, &Parent), With<TimerText>>,
+) {
+    for (mut text, parent) in query.iter_mut() {
+        let Ok(timer) = timers.get(parent.get()) else {
+            continue;
+        };
+
+        let section = &mut text.sections[0];
+        section.value = if timer.hide {
+            "".to_string()
+        } else if timer.mode == TimerMode::Untimed && !timer.adjusting_interval {
+            "--.--".to_string()
+        } else if let Some(countdown) = timer.resume_countdown() {
+            format!("{}", countdown.as_secs_f32().ceil())
+        } else if timer.mode == TimerMode::Stopwatch && !timer.adjusting_interval {
+            format_timer_time(timer.elapsed(), timer.elapsed(), false)
+        } else {
+            let time = match display.time {
+                TimeDisplay::Remaining => timer.remaining(),
+                TimeDisplay::Elapsed => timer.elapsed(),
+            };
+            format_timer_time(
+                time,
+                timer.interval(),
+                display.time == TimeDisplay::Remaining,
+            )
+        };
+        section.style.color = if timer.in_warning(display.warning) {
+            TIMER_WARNING_COLOR
+        } else {
+            TIMER_TEXT_COLOR
+        };
+    }
+}
+
+fn update_timer_progress(
+    time: Res<Time<Real>>,
+    display: Res<TimerDisplay>,
+    timers: Query<&Timer>,
+    mut query: Query<(&mut Style, &mut BackgroundColor, &Parent), With<TimerProgress>>,
+) {
+    for (mut style, mut color, parent) in query.iter_mut() {
+        let Ok(timer) = timers.get(parent.get()) else {
+            continue;
+        };
+
+        let fraction = if timer.hide || timer.mode != TimerMode::Interval {
+            0.0
+        } else {
+            timer.fraction_remaining()
+        };
+        style.width = Val::Percent(fraction * 100.0);
+
+        color.0 = if timer.in_warning(display.warning) {
+            let pulse = if timer.is_paused() {
+                1.0
+            } else {
+                0.65 + 0.35 * (time.elapsed_seconds() * std::f32::consts::TAU * 1.5).cos()
+            };
+            TIMER_WARNING_COLOR.with_a(pulse)
+        } else {
+            TIMER_PROGRESS_COLOR
+        };
+    }
+}
+
+fn setup_pose_timer(mut commands: Commands) {
+    // ui camera
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: 10000,
+                ..default()
+            },
+            ..default()
+        },
+        UI_RENDER_LAYER,
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::End,
+                    justify_content: JustifyContent::End,
+                    ..default()
+                },
+                ..default()
+            },
+            UI_RENDER_LAYER,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(150.0),
+                            height: Val::Px(65.0),
+                            border: UiRect::all(Val::Px(5.0)),
+                            // horizontally center child text
+                            justify_content: JustifyContent::Center,
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+
+                        ..default()
+                    },
+                    Timer::new(Duration::from_secs_f32(TIMER_INTERVAL)),
+                    PoseTimer,
+                    On::<PointerEvent>::run(timer_interaction),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: Handle::default(),
+                                font_size: 40.0,
+                                color: TIMER_TEXT_COLOR,
+                            },
+                        ),
+                        TimerText,
+                    ));
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                left: Val::Px(0.0),
+                                bottom: Val::Px(0.0),
+                                width: Val::Percent(100.0),
+                                height: Val::Px(6.0),
+                                ..default()
+                            },
+                            background_color: TIMER_PROGRESS_COLOR.into(),
+                            ..default()
+                        },
+                        TimerProgress,
+                    ));
+                });
+        });
+}
+
+fn timer_interaction(
+    mut timers: Query<&mut Timer>,
+    mut wrapping_cursor: ResMut<NextState<WrappingCursorState>>,
+    mut wrap_events: EventReader<Wrap>,
+    event: Listener<PointerEvent>,
+) {
+    let Ok(mut timer) = timers.get_mut(event.listener()) else {
+        return;
+    };
+
+    match &**event {
+        PointerEvent::DragStart(_) => {
+            timer.adjusting_interval = true;
+            timer.mode = TimerMode::Interval;
+            wrapping_cursor.set(WrappingCursorState::On);
+        }
+        PointerEvent::Drag(e) => {
+            // ignoring pointer wrapping. this is not an ideal solution as one could imagine that there is
+            // multiple Drag events in a single frame, but in practice that isn't the case in the current version
+            // of bevy_mod_picking.
+            if wrap_events.read().len() == 0 {
+                let interval = (timer.interval().as_secs_f32() + e.delta.x * 0.01).max(0.0);
+                timer.set_interval(Duration::from_secs_f32(interval));
+            }
+        }
+        PointerEvent::DragEnd(_) => {
+            timer.adjusting_interval = false;
+            wrapping_cursor.set(WrappingCursorState::Off);
+        }
+        PointerEvent::Up(e) => {
+            if !timer.adjusting_interval {
+                match e.button {
+                    PointerButton::Primary => {
+                        timer.toggle_pause();
+                    }
+                    PointerButton::Secondary => {
+                        timer.toggle_hide();
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+fn timer_test_app(interval: Duration) -> (App, Entity) {
+    let mut app = App::new();
+    app.init_resource::<Time<Virtual>>()
+        .add_event::<TimerEvent>()
+        .add_event::<NextPose>()
+        .add_systems(Update, tick_timers);
+    let entity = app.world.spawn(Timer::new(interval)).id();
+    (app, entity)
+}
+
+#[cfg(test)]
+fn advance(app: &mut App, delta: Duration) -> usize {
+    app.world.resource_mut::<Time<Virtual>>().advance_by(delta);
+    app.update();
+    let events = app.world.resource::<Events<TimerEvent>>();
+    events.get_reader().read(events).count()
+}
+
+#[test]
+fn test_timer_fires_after_interval() {
+    let (mut app, entity) = timer_test_app(Duration::from_secs(3));
+
+    assert_eq!(advance(&mut app, Duration::from_secs(2)), 0);
+    assert_eq!(
+        app.world.get::<Timer>(entity).unwrap().elapsed(),
+        Duration::from_secs(2)
+    );
+
+    assert_eq!(advance(&mut app, Duration::from_millis(1500)), 1);
+    // The time past the interval is carried over into the next one.
+    assert_eq!(
+        app.world.get::<Timer>(entity).unwrap().elapsed(),
+        Duration::from_millis(500)
+    );
+}
+
+#[test]
+fn test_timer_wraps_around_once_for_large_delta() {
+    let mut timer = Timer::new(Duration::from_secs(2));
+
+    assert!(timer.tick(Duration::from_secs(5)));
+    assert_eq!(timer.elapsed(), Duration::from_secs(1));
+    assert!(!timer.tick(Duration::from_millis(900)));
+    assert!(timer.tick(Duration::from_millis(100)));
+    assert_eq!(timer.elapsed(), Duration::ZERO);
+}
+
+#[test]
+fn test_timer_paused() {
+    let (mut app, entity) = timer_test_app(Duration::from_secs(1));
+
+    app.world.get_mut::<Timer>(entity).unwrap().set_pause(true);
+    assert_eq!(advance(&mut app, Duration::from_secs(5)), 0);
+    assert_eq!(
+        app.world.get::<Timer>(entity).unwrap().elapsed(),
+        Duration::ZERO
+    );
+
+    app.world.get_mut::<Timer>(entity).unwrap().set_pause(false);
+    assert_eq!(advance(&mut app, Duration::from_secs(1)), 1);
+}
+
+#[test]
+fn test_timer_resume_with_countdown() {
+    let (mut app, entity) = timer_test_app(Duration::from_secs(2));
+
+    assert_eq!(advance(&mut app, Duration::from_secs(1)), 0);
+    app.world.get_mut::<Timer>(entity).unwrap().set_pause(true);
+    assert_eq!(advance(&mut app, Duration::from_secs(60)), 0);
+
+    app.world
+        .get_mut::<Timer>(entity)
+        .unwrap()
+        .resume_with_countdown(Duration::from_secs(3));
+    assert_eq!(advance(&mut app, Duration::from_secs(2)), 0);
+    let timer = app.world.get::<Timer>(entity).unwrap();
+    assert_eq!(timer.resume_countdown(), Some(Duration::from_secs(1)));
+    assert_eq!(timer.elapsed(), Duration::from_secs(1));
+
+    // The part of the frame after the countdown ended counts towards the interval.
+    assert_eq!(advance(&mut app, Duration::from_millis(1500)), 0);
+    let timer = app.world.get::<Timer>(entity).unwrap();
+    assert_eq!(timer.resume_countdown(), None);
+    assert_eq!(timer.elapsed(), Duration::from_millis(1500));
+    assert_eq!(advance(&mut app, Duration::from_millis(500)), 1);
+}
+
+#[test]
+fn test_timer_stopwatch() {
+    let mut timer = Timer::new(Duration::from_secs(1));
+    timer.mode = TimerMode::Stopwatch;
+
+    assert!(!timer.tick(Duration::from_secs(10)));
+    assert_eq!(timer.elapsed(), Duration::from_secs(10));
+    assert_eq!(timer.skip(), Duration::from_secs(10));
+    assert_eq!(timer.elapsed(), Duration::ZERO);
+}
+
+#[test]
+fn test_next_pose() {
+    let (mut app, entity) = timer_test_app(Duration::from_secs(60));
+    app.add_systems(Update, next_pose.before(tick_timers));
+    app.world.entity_mut(entity).insert(PoseTimer);
+    app.world.get_mut::<Timer>(entity).unwrap().mode = TimerMode::Untimed;
+
+    assert_eq!(advance(&mut app, Duration::from_secs(100)), 0);
+    app.world.send_event(NextPose);
+    app.world
+        .resource_mut::<Time<Virtual>>()
+        .advance_by(Duration::from_secs(1));
+    app.update();
+
+    let events = app.world.resource::<Events<TimerEvent>>();
+    let sent: Vec<_> = events.get_reader().read(events).copied().collect();
+    assert_eq!(
+        sent,
+        [TimerEvent {
+            timer: entity,
+            elapsed: Duration::from_secs(100),
+        }]
+    );
+    assert_eq!(
+        app.world.get::<Timer>(entity).unwrap().elapsed(),
+        Duration::ZERO
+    );
+}
+
+#[test]
+fn test_next_pose_when_interval_elapses() {
+    let (mut app, entity) = timer_test_app(Duration::from_secs(1));
+    app.add_systems(Update, next_pose.before(tick_timers));
+    app.world.entity_mut(entity).insert(PoseTimer);
+
+    assert_eq!(advance(&mut app, Duration::from_millis(900)), 0);
+    // Skipping on the frame the interval elapses only skips one pose.
+    app.world.send_event(NextPose);
+    assert_eq!(advance(&mut app, Duration::from_millis(200)), 1);
+    assert_eq!(
+        app.world.get::<Timer>(entity).unwrap().elapsed(),
+        Duration::ZERO
+    );
+}
+
+#[test]
+fn test_timer_remaining() {
+    let mut timer = Timer::new(Duration::from_secs(60));
+    timer.tick(Duration::from_secs(45));
+
+    assert_eq!(timer.remaining(), Duration::from_secs(15));
+    assert_eq!(timer.fraction_remaining(), 0.25);
+    assert!(!timer.in_warning(Duration::from_secs(10)));
+    assert!(timer.in_warning(Duration::from_secs(15)));
+    assert!(!timer.in_warning(Duration::ZERO));
+    assert_eq!(
+        format_timer_time(timer.remaining(), timer.interval(), true),
+        "00:15"
+    );
+    assert_eq!(
+        format_timer_time(Duration::from_millis(2500), Duration::from_secs(3), false),
+        "02.50"
+    );
+
+    // Counting down, the last second only shows zero once it is over.
+    timer.tick(Duration::from_millis(14_700));
+    assert_eq!(
+        format_timer_time(timer.remaining(), timer.interval(), true),
+        "00:01"
+    );
+    assert_eq!(
+        format_timer_time(timer.elapsed(), timer.interval(), false),
+        "00:59"
+    );
+}
+
+#[test]
+fn test_parse_minutes_seconds() {
+    assert_eq!(parse_minutes_seconds("90"), Some(90.0));
+    assert_eq!(parse_minutes_seconds("02:30"), Some(150.0));
+    assert_eq!(parse_minutes_seconds(" 5:00 "), Some(300.0));
+    assert_eq!(parse_minutes_seconds("5m"), None);
+    assert_eq!(format_minutes_seconds(Duration::from_secs(150)), "02:30");
+    assert_eq!(format_minutes_seconds(MIN_INTERVAL), "00:00.1");
+    assert_eq!(parse_minutes_seconds("00:00.1"), Some(0.1));
+}
+
+#[test]
+fn test_timer_adjusting_interval() {
+    let mut timer = Timer::new(Duration::from_secs(1));
+    timer.tick(Duration::from_millis(300));
+
+    timer.adjusting_interval = true;
+    timer.set_interval(Duration::from_secs(4));
+    assert!(!timer.tick(Duration::from_secs(10)));
+    assert_eq!(timer.elapsed(), Duration::from_secs(4));
+
+    timer.adjusting_interval = false;
+    assert_eq!(timer.elapsed(), Duration::from_millis(300));
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+
+use crate::references::{NewPose, ReferenceSet, References};
+use crate::rotation::uniform_rotation;
+use crate::timer::{PoseTimer, Timer};
+use crate::MainCamera;
+
+/// Slowly turns the shown references during a pose.
+pub struct TurntablePlugin;
+
+impl Plugin for TurntablePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TurntableSettings>()
+            .add_systems(Update, (start_turntable, turn).chain().after(ReferenceSet));
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TurntableSettings {
+    pub enabled: bool,
+    pub axis: TurntableAxis,
+    /// Radians per second.
+    pub speed: f32,
+    /// Stop turning once the pose interval elapsed, e.g. to finish a drawing in stopwatch mode.
+    pub stop_at_end: bool,
+}
+
+impl Default for TurntableSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            axis: TurntableAxis::Up,
+            speed: 20f32.to_radians(),
+            stop_at_end: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurntableAxis {
+    /// Around the world up, like a sculpture on a turntable.
+    Up,
+    /// Around the horizontal axis of the view, tumbling towards the camera.
+    Horizontal,
+    /// A different random axis for every pose.
+    Random,
+}
+
+/// A reference turning during the current pose.
+#[derive(Component, Debug, Clone, Copy)]
+struct Turntable {
+    axis: Vec3,
+    /// The pose time the reference was last turned at.
+    elapsed: f32,
+}
+
+fn start_turntable(
+    mut commands: Commands,
+    settings: Res<TurntableSettings>,
+    refs: Res<References>,
+    mut new_poses: EventReader<NewPose>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+) {
+    if new_poses.read().count() == 0 {
+        return;
+    }
+
+    let right = camera.get_single().map_or(Vec3::X, |camera| camera.right());
+    let mut rng = rand::thread_rng();
+    for &shown in refs.shown.iter() {
+        let axis = match settings.axis {
+            TurntableAxis::Up => Vec3::Y,
+            TurntableAxis::Horizontal => right,
+            TurntableAxis::Random => uniform_rotation(&mut rng) * Vec3::Z,
+        };
+        // Replaces the turntable of the previous pose.
+        commands
+            .entity(refs.references[shown].entity)
+            .insert(Turntable { axis, elapsed: 0.0 });
+    }
+}
+
+/// Applies the turn since the last frame on top of the reference's rotation, so dragging the
+/// reference still works while it turns. The angle follows the pose timer, so pausing the timer
+/// also stops the turntable.
+fn turn(
+    settings: Res<TurntableSettings>,
+    refs: Res<References>,
+    timer: Query<&Timer, With<PoseTimer>>,
+    mut references: Query<(&mut Transform, &mut Turntable)>,
+) {
+    let Ok(timer) = timer.get_single() else {
+        return;
+    };
+    // The timer shows the interval instead of the elapsed time meanwhile, and during the check
+    // the references are turned by the check.
+    if timer.adjusting_interval || timer.held {
+        return;
+    }
+
+    let elapsed = if settings.stop_at_end {
+        timer.elapsed().min(timer.interval())
+    } else {
+        timer.elapsed()
+    };
+    let elapsed = elapsed.as_secs_f32();
+
+    for &shown in refs.shown.iter() {
+        let Ok((mut transform, mut turntable)) = references.get_mut(refs.references[shown].entity)
+        else {
+            continue;
+        };
+        // Keeps track of the time while disabled, so enabling it again doesn't jump. The time going
+        // back, e.g. as the interval elapsed, isn't turned back either.
+        if settings.enabled && elapsed > turntable.elapsed {
+            let angle = (elapsed - turntable.elapsed) * settings.speed;
+            let rotation = Quat::from_axis_angle(turntable.axis, angle);
+            transform.rotation = (rotation * transform.rotation).normalize();
+        }
+        turntable.elapsed = elapsed;
+    }
+}
+
+#[test]
+fn test_turn_while_adjusting_interval() {
+    use std::time::Duration;
+
+    use crate::references::test_reference;
+
+    let mut app = App::new();
+    app.insert_resource(TurntableSettings {
+        enabled: true,
+        ..default()
+    })
+    .add_systems(Update, turn);
+    let entity = app
+        .world
+        .spawn((
+            Transform::default(),
+            Turntable {
+                axis: Vec3::Y,
+                elapsed: 0.0,
+            },
+        ))
+        .id();
+    app.insert_resource(References {
+        references: vec![test_reference("a", entity)],
+        disabled_references: default(),
+        current_reference: Some(0),
+        shown: vec![0],
+        loading_folder: Handle::default(),
+    });
+    let mut timer = Timer::new(Duration::from_secs(60));
+    timer.tick(Duration::from_secs(1));
+    let timer = app.world.spawn((timer, PoseTimer)).id();
+    let rotation = |app: &App| app.world.get::<Transform>(entity).unwrap().rotation;
+
+    app.update();
+    let turned = rotation(&app);
+    assert!(turned.angle_between(Quat::IDENTITY) > 0.0);
+
+    // The reference doesn't turn towards the end of the interval and back.
+    app.world
+        .get_mut::<Timer>(timer)
+        .unwrap()
+        .adjusting_interval = true;
+    app.update();
+    assert_eq!(rotation(&app), turned);
+    app.world
+        .get_mut::<Timer>(timer)
+        .unwrap()
+        .adjusting_interval = false;
+    app.update();
+    assert_eq!(rotation(&app), turned);
+}
+
+#[test]
+fn test_turn_until_check() {
+    use std::time::Duration;
+
+    use crate::check::{CheckPlugin, CheckSettings};
+    use crate::references::{test_reference, EndPose};
+    use crate::timer::TimerEvent;
+
+    let mut app = App::new();
+    app.init_resource::<Time>()
+        .init_resource::<ClearColor>()
+        .insert_resource(CheckSettings {
+            enabled: true,
+            ..default()
+        })
+        .insert_resource(TurntableSettings {
+            enabled: true,
+            ..default()
+        })
+        .add_event::<TimerEvent>()
+        .add_event::<EndPose>()
+        .add_event::<NewPose>()
+        .add_plugins((CheckPlugin, TurntablePlugin));
+    let entity = app
+        .world
+        .spawn((
+            Transform::default(),
+            Turntable {
+                axis: Vec3::Y,
+                elapsed: 0.0,
+            },
+        ))
+        .id();
+    app.insert_resource(References {
+        references: vec![test_reference("a", entity)],
+        disabled_references: default(),
+        current_reference: Some(0),
+        shown: vec![0],
+        loading_folder: Handle::default(),
+    });
+    let mut timer = Timer::new(Duration::from_secs(2));
+    timer.tick(Duration::from_millis(1900));
+    let timer = app.world.spawn((timer, PoseTimer)).id();
+    let rotation = |app: &App| app.world.get::<Transform>(entity).unwrap().rotation;
+
+    app.update();
+    let turned = rotation(&app);
+    assert!(turned.angle_between(Quat::IDENTITY) > 0.0);
+
+    // The interval elapses and the check starts where the turntable left off, without the
+    // turntable turning back to the start of the next interval.
+    let mut pose_timer = app.world.get_mut::<Timer>(timer).unwrap();
+    assert!(pose_timer.tick(Duration::from_millis(200)));
+    app.world.send_event(TimerEvent {
+        timer,
+        elapsed: Duration::from_secs(2),
+    });
+    app.update();
+    assert!(rotation(&app).angle_between(turned) < 1e-4);
+}